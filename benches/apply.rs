@@ -0,0 +1,78 @@
+//! Compares the cost of chaining methods on a big structure with `Apply`,
+//! which moves the structure at each call, and with `ApplyMut`, which
+//! modifies it in place.
+//!
+//! No benchmarking framework is used, so that `shpat` keeps having no
+//! dependencies. Run it with `cargo bench --bench apply`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use shpat::prelude::*;
+
+/// Number of times each chain is run.
+const ITERATIONS: u32 = 100_000;
+
+/// A structure big enough for moves to be noticeable.
+struct BigStruct {
+    /// A field modified by each step of the chain.
+    counter: usize,
+    /// Some data which should not be moved.
+    _payload: [u8; 4096],
+}
+
+impl BigStruct {
+    /// Creates a new, zeroed, `BigStruct`.
+    fn new() -> BigStruct {
+        BigStruct {
+            counter: 0,
+            _payload: [0; 4096],
+        }
+    }
+}
+
+/// Runs `f` `ITERATIONS` times, and returns the mean time per iteration.
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let mut by_ref = BigStruct::new();
+    let mut by_value = Some(BigStruct::new());
+
+    let apply = measure(|| {
+        let b = black_box(by_value.take().unwrap())
+            .apply(|b| b.counter += 1)
+            .apply(|b| b.counter += 1)
+            .apply(|b| b.counter += 1)
+            .apply(|b| b.counter += 1);
+        by_value = Some(black_box(b));
+    });
+
+    let apply_mut = measure(|| {
+        black_box(&mut by_ref)
+            .apply_mut(|b| b.counter += 1)
+            .apply_mut(|b| b.counter += 1)
+            .apply_mut(|b| b.counter += 1)
+            .apply_mut(|b| b.counter += 1);
+    });
+
+    let boxed = measure(|| {
+        let b = black_box(Box::new(by_value.take().unwrap()))
+            .apply(|b| b.counter += 1)
+            .apply(|b| b.counter += 1)
+            .apply(|b| b.counter += 1)
+            .apply(|b| b.counter += 1);
+        by_value = Some(*black_box(b));
+    });
+
+    println!("Apply (by value):      {:>10?}/iter", apply);
+    println!("Apply (boxed):         {:>10?}/iter", boxed);
+    println!("ApplyMut (in place):   {:>10?}/iter", apply_mut);
+}
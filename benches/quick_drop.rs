@@ -0,0 +1,50 @@
+//! Compares the cost, for the calling thread, of dropping objects inline, in a
+//! newly spawned thread for each object, and with `QuickDrop`, which sends
+//! them to a single background thread.
+//!
+//! No benchmarking framework is used, so that `shpat` keeps having no
+//! dependencies. Run it with `cargo bench --bench quick_drop`.
+
+use std::{
+    hint::black_box,
+    thread,
+    time::{Duration, Instant},
+};
+
+use shpat::prelude::*;
+
+/// Number of objects dropped by each strategy.
+const ITERATIONS: u32 = 10_000;
+
+/// Creates a medium-sized object, which is relatively cheap to drop.
+fn medium() -> Vec<String> {
+    (0..16).map(|i| i.to_string()).collect()
+}
+
+/// Creates `ITERATIONS` objects with `make`, drops them with `f`, and returns
+/// the mean time spent in `f`.
+fn measure<T, M: FnMut() -> T, F: FnMut(T)>(mut make: M, mut f: F) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let value = black_box(make());
+        let start = Instant::now();
+        f(value);
+        total += start.elapsed();
+    }
+    total / ITERATIONS
+}
+
+fn main() {
+    // Starts the reaper thread, so that its creation is not measured.
+    ().quick_drop();
+
+    let inline = measure(medium, drop);
+    let spawn = measure(medium, |v| {
+        thread::spawn(move || drop(v));
+    });
+    let quick_drop = measure(medium, QuickDrop::quick_drop);
+
+    println!("drop (inline):             {:>10?}/iter", inline);
+    println!("thread::spawn per object:  {:>10?}/iter", spawn);
+    println!("QuickDrop::quick_drop:     {:>10?}/iter", quick_drop);
+}
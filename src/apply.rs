@@ -27,9 +27,9 @@
 //! # Performance
 //!
 //! This implementation of `Apply` requires the object to move at each new call
-//! to `apply`. Although this has not been benchmarked, these moves may cause
-//! some slowdown issues on performance-critical operations or if the size of
-//! the `appl`ied structure is big.
+//! to `apply`. These moves may cause some slowdown issues on
+//! performance-critical operations or if the size of the `appl`ied structure
+//! is big (see the `apply` benchmark, which can be run with `cargo bench`).
 //!
 //! A way to avoid this slowdown is to wrap the structure in a `Box`, call the
 //! needed `apply`, then dereference it. This way, the reference to the object
@@ -51,8 +51,24 @@
 //!
 //! assert_eq!(b, BigStruct { counter: 1 });
 //! ```
+//!
+//! Another way is to use the `ApplyMut` trait, whose methods take and return
+//! a mutable reference. The object is modified in place and never moves. As
+//! no ownership is required, it also works on unsized types, such as slices,
+//! `str` or trait objects:
+//!
+//! ```rust
+//! use shpat::prelude::*;
+//!
+//! let mut v = vec![3, 1, 2];
+//! v.as_mut_slice()
+//!     .apply_mut(|s| s.sort())
+//!     .apply_mut(|s| s.reverse());
+//!
+//! assert_eq!(v, [3, 2, 1]);
+//! ```
 
-use crate::unwrappable::Unwrappable;
+use crate::unwrappable::{UnwrapAll, Unwrappable};
 
 /// Allows to perform method chaining on functions which take reference.
 pub trait Apply: Sized {
@@ -126,16 +142,347 @@ pub trait Apply: Sized {
     /// v.apply_unwrap(Vec::pop)
     ///     .apply_unwrap(Vec::pop);
     /// ```
+    #[track_caller]
     fn apply_unwrap<T, U: Unwrappable<T>, F: FnOnce(&mut Self) -> U>(mut self, f: F) -> Self {
         Unwrappable::unwrap(f(&mut self));
         self
     }
+
+    /// Applies `f` to `self`, unwrapping every layer of the nested
+    /// `Unwrappable` returned by `f`, such as a `Result<Option<T>, E>`.
+    ///
+    /// The type of the innermost value, `T`, can not be inferred, and has to
+    /// be specified.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if any layer of the value returned by `f` is a
+    /// failure. The panic message says which layer failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use shpat::prelude::*;
+    ///
+    /// let map = HashMap::new()
+    ///     .apply(|m| m.insert("port", String::from("8080")))
+    ///     .apply_unwrap_all::<u16, _, _, _>(|m| m.get("port").map(|p| p.parse::<u16>()));
+    /// ```
+    #[track_caller]
+    fn apply_unwrap_all<T, D, U: UnwrapAll<T, D>, F: FnOnce(&mut Self) -> U>(
+        mut self,
+        f: F,
+    ) -> Self {
+        UnwrapAll::unwrap_all(f(&mut self));
+        self
+    }
+
+    /// Applies `f` to `self`, unwrapping the value returned by `Unwrappable`
+    /// with a custom panic message.
+    ///
+    /// This behaves exactly like `apply_unwrap`, except that `msg` is
+    /// included in the panic message, which helps to find which step of a
+    /// long chain failed.
+    ///
+    /// # Panics
+    ///
+    /// This method is guaranteed to panic if `f` returns a panickable value.
+    ///
+    /// # Examples
+    ///
+    /// ```should_panic
+    /// use shpat::prelude::*;
+    ///
+    /// let v = vec![2];
+    /// v.apply_expect("first pop", Vec::pop)
+    ///     .apply_expect("second pop", Vec::pop);
+    /// ```
+    #[track_caller]
+    fn apply_expect<T, U: Unwrappable<T>, F: FnOnce(&mut Self) -> U>(
+        mut self,
+        msg: &str,
+        f: F,
+    ) -> Self {
+        Unwrappable::expect(f(&mut self), msg);
+        self
+    }
+
+    /// Applies `f` to `self`, returning `self` wrapped in the same kind of
+    /// `Unwrappable` as the one returned by `f`.
+    ///
+    /// This is the non-panicking counterpart of `apply_unwrap`: if `f` returns
+    /// a `Result`, then `apply_try` returns a `Result<Self, E>`, and if `f`
+    /// returns an `Option`, then it returns an `Option<Self>`. The chain can
+    /// then be continued with the `?` operator.
+    ///
+    /// # Errors
+    ///
+    /// Returns the failure returned by `f`, if any. In this case, `self` is
+    /// dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// fn pop_twice(v: Vec<u8>) -> Option<Vec<u8>> {
+    ///     Some(v.apply_try(Vec::pop)?.apply_try(Vec::pop)?)
+    /// }
+    ///
+    /// assert_eq!(pop_twice(vec![1, 2, 3]), Some(vec![1]));
+    /// assert_eq!(pop_twice(vec![1]), None);
+    /// ```
+    fn apply_try<T, U: Unwrappable<T>, F: FnOnce(&mut Self) -> U>(mut self, f: F) -> U::Map<Self> {
+        let tmp = f(&mut self);
+        Unwrappable::rewrap(tmp, self)
+    }
+
+    /// Applies `f` to `self` if `condition` is `true`, discarding the value
+    /// returned by `f`.
+    ///
+    /// `self` is returned untouched if `condition` is `false`, so that the
+    /// chain is never interrupted.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let verbose = false;
+    /// let args = vec!["run"]
+    ///     .apply_if(verbose, |a| a.push("--verbose"))
+    ///     .apply(|a| a.push("--release"));
+    ///
+    /// assert_eq!(args, ["run", "--release"]);
+    /// ```
+    fn apply_if<A, F: FnOnce(&mut Self) -> A>(mut self, condition: bool, f: F) -> Self {
+        if condition {
+            f(&mut self);
+        }
+        self
+    }
+
+    /// Applies `f` to `self` and the value contained in `value`, if it is a
+    /// `Some` variant, discarding the value returned by `f`.
+    ///
+    /// `self` is returned untouched if `value` is `None`.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use shpat::prelude::*;
+    ///
+    /// let port = Some(8080);
+    /// let host = None;
+    /// let config = HashMap::new()
+    ///     .apply_if_some(port, |m, p| m.insert("port", p))
+    ///     .apply_if_some(host, |m, h| m.insert("host", h));
+    ///
+    /// assert_eq!(config.get("port"), Some(&8080));
+    /// assert_eq!(config.get("host"), None);
+    /// ```
+    fn apply_if_some<V, A, F: FnOnce(&mut Self, V) -> A>(mut self, value: Option<V>, f: F) -> Self {
+        if let Some(value) = value {
+            f(&mut self, value);
+        }
+        self
+    }
+
+    /// Applies `f` to `self` if `predicate` returns `true` when called on
+    /// `self`, discarding the value returned by `f`.
+    ///
+    /// `self` is returned untouched if `predicate` returns `false`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let v = vec![3, 1, 2]
+    ///     .apply_when(|v| v.len() > 2, |v| v.sort())
+    ///     .apply_when(Vec::is_empty, |v| v.push(0));
+    ///
+    /// assert_eq!(v, [1, 2, 3]);
+    /// ```
+    fn apply_when<A, P: FnOnce(&Self) -> bool, F: FnOnce(&mut Self) -> A>(
+        mut self,
+        predicate: P,
+        f: F,
+    ) -> Self {
+        if predicate(&self) {
+            f(&mut self);
+        }
+        self
+    }
+
+    /// Applies `f` to `self`, restoring `self` to its original value if `f`
+    /// returns a failure.
+    ///
+    /// A copy of `self` is made before calling `f`. If `f` fails, then the
+    /// possibly partially modified `self` is replaced by this copy. This gives
+    /// all-or-nothing semantics to a multi-step modification. The value
+    /// returned by `f` is returned as well, so that the failure can be
+    /// handled.
+    ///
+    /// Types which can be restored more cheaply than by cloning them should
+    /// implement `Snapshot`, and use `apply_atomic_snapshot`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let (v, res) = vec![1, 2].apply_atomic(|v| {
+    ///     v.push(3);
+    ///     "four".parse().map(|n| v.push(n))
+    /// });
+    ///
+    /// assert!(res.is_err());
+    /// assert_eq!(v, [1, 2]);
+    /// ```
+    fn apply_atomic<T, U: Unwrappable<T>, F: FnOnce(&mut Self) -> U>(mut self, f: F) -> (Self, U)
+    where
+        Self: Clone,
+    {
+        let original = self.clone();
+        let tmp = f(&mut self);
+
+        if Unwrappable::is_success(&tmp) {
+            (self, tmp)
+        } else {
+            (original, tmp)
+        }
+    }
+
+    /// Applies `f` to `self`, restoring `self` to the state it had before the
+    /// call if `f` returns a failure.
+    ///
+    /// This is the same as `apply_atomic`, but uses the `Snapshot` trait
+    /// instead of `Clone` to save and restore `self`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// // An append-only log, which can be restored by truncating it.
+    /// struct Log(Vec<String>);
+    ///
+    /// impl Snapshot for Log {
+    ///     type Checkpoint = usize;
+    ///
+    ///     fn snapshot(&self) -> usize {
+    ///         self.0.len()
+    ///     }
+    ///
+    ///     fn restore(&mut self, len: usize) {
+    ///         self.0.truncate(len);
+    ///     }
+    /// }
+    ///
+    /// let (log, res) = Log(Vec::new()).apply_atomic_snapshot(|l| {
+    ///     l.0.push(String::from("starting"));
+    ///     None::<()>
+    /// });
+    ///
+    /// assert!(res.is_none());
+    /// assert!(log.0.is_empty());
+    /// ```
+    fn apply_atomic_snapshot<T, U: Unwrappable<T>, F: FnOnce(&mut Self) -> U>(
+        mut self,
+        f: F,
+    ) -> (Self, U)
+    where
+        Self: Snapshot,
+    {
+        let checkpoint = self.snapshot();
+        let tmp = f(&mut self);
+
+        if !Unwrappable::is_success(&tmp) {
+            self.restore(checkpoint);
+        }
+
+        (self, tmp)
+    }
 }
 
 // Automatic implementation of the `Apply` trait for any sized type.
 impl<T: Sized> Apply for T {}
 
+/// Allows to save the state of an object, and to restore it later.
+///
+/// This is used by `Apply::apply_atomic_snapshot`, and should be implemented
+/// for types which can be saved more cheaply than by cloning them.
+pub trait Snapshot {
+    /// The data needed to restore the object to a previous state.
+    type Checkpoint;
+
+    /// Returns a checkpoint of the current state of `self`.
+    fn snapshot(&self) -> Self::Checkpoint;
+
+    /// Restores `self` to the state it had when `checkpoint` was created.
+    fn restore(&mut self, checkpoint: Self::Checkpoint);
+}
+
+/// Allows to perform method chaining on functions which take reference,
+/// without moving the object.
+pub trait ApplyMut {
+    /// Applies `f` to `self`, returning the modified `self`, and the value
+    /// returned by the function.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let mut v = vec![1, 2, 3, 4];
+    /// let (v_ref, popped) = v.apply_mut_keep(|v| v.pop());
+    ///
+    /// assert_eq!(v_ref, &[1, 2, 3]);
+    /// assert_eq!(popped, Some(4));
+    /// ```
+    fn apply_mut_keep<A, F: FnOnce(&mut Self) -> A>(&mut self, f: F) -> (&mut Self, A) {
+        let tmp = f(self);
+        (self, tmp)
+    }
+
+    /// Applies `f` to `self`, discarding the value returned by `f`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let mut s = String::from("hello");
+    /// s.as_mut_str()
+    ///     .apply_mut(|s| s.make_ascii_uppercase());
+    ///
+    /// assert_eq!(s, "HELLO");
+    /// ```
+    fn apply_mut<A, F: FnOnce(&mut Self) -> A>(&mut self, f: F) -> &mut Self {
+        f(self);
+        self
+    }
+
+    /// Applies `f` to `self`, unwrapping the value returned by `Unwrappable`.
+    ///
+    /// # Panics
+    ///
+    /// This method is guaranteed to panic if `f` returns a panickable value.
+    ///
+    /// ```should_panic
+    /// use shpat::prelude::*;
+    ///
+    /// let mut v = vec![2];
+    /// v.apply_mut_unwrap(Vec::pop)
+    ///     .apply_mut_unwrap(Vec::pop);
+    /// ```
+    #[track_caller]
+    fn apply_mut_unwrap<T, U: Unwrappable<T>, F: FnOnce(&mut Self) -> U>(
+        &mut self,
+        f: F,
+    ) -> &mut Self {
+        Unwrappable::unwrap(f(self));
+        self
+    }
+}
+
+// Automatic implementation of the `ApplyMut` trait for any type, including
+// unsized ones.
+impl<T: ?Sized> ApplyMut for T {}
+
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod apply {
     use super::*;
 
@@ -183,4 +530,202 @@ mod apply {
     fn unwrap_panic_path() {
         let _ = Vec::<()>::new().apply_unwrap(|v| v.pop());
     }
+
+    #[test]
+    fn unwrap_all_non_panic_path() {
+        let left = HashMap::new()
+            .apply(|m| m.insert("foo", "42"))
+            .apply_unwrap_all::<u32, _, _, _>(|m| m.get("foo").map(|v| v.parse::<u32>()));
+        let right = HashMap::new().apply(|m| m.insert("foo", "42"));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    #[should_panic(expected = "layer 2")]
+    fn unwrap_all_panic_path() {
+        let _ = HashMap::new()
+            .apply(|m| m.insert("foo", "bar"))
+            .apply_unwrap_all::<u32, _, _, _>(|m| m.get("foo").map(|v| v.parse::<u32>()));
+    }
+
+    #[test]
+    #[should_panic(expected = "replacing bar")]
+    fn expect_panic_path() {
+        let _ = HashMap::new()
+            .apply(|m| m.insert("foo", 101))
+            .apply_expect("replacing foo", |m| m.insert("foo", 42))
+            .apply_expect("replacing bar", |m| m.insert("bar", 1969));
+    }
+
+    #[test]
+    fn atomic_success_path() {
+        let (left, res) = HashMap::new()
+            .apply(|m| m.insert("foo", 101))
+            .apply_atomic(|m| {
+                m.insert("bar", 42);
+                m.insert("foo", 1969)
+            });
+
+        let right = HashMap::new()
+            .apply(|m| m.insert("foo", 1969))
+            .apply(|m| m.insert("bar", 42));
+
+        assert_eq!(left, right);
+        assert_eq!(res, Some(101));
+    }
+
+    #[test]
+    fn atomic_failure_path() {
+        let (left, res) = HashMap::new()
+            .apply(|m| m.insert("foo", 101))
+            .apply_atomic(|m| {
+                m.insert("foo", 1969);
+                m.insert("bar", 42)
+            });
+
+        let right = HashMap::new().apply(|m| m.insert("foo", 101));
+
+        assert_eq!(left, right);
+        assert_eq!(res, None);
+    }
+
+    #[test]
+    fn atomic_snapshot() {
+        #[derive(Debug, PartialEq)]
+        struct Counter(usize);
+
+        impl Snapshot for Counter {
+            type Checkpoint = usize;
+
+            fn snapshot(&self) -> usize {
+                self.0
+            }
+
+            fn restore(&mut self, checkpoint: usize) {
+                self.0 = checkpoint;
+            }
+        }
+
+        let (c, res) = Counter(0).apply(|c| c.0 += 1).apply_atomic_snapshot(|c| {
+            c.0 += 1;
+            Err::<(), _>("nope")
+        });
+
+        assert_eq!(c, Counter(1));
+        assert_eq!(res, Err("nope"));
+
+        let (c, res) = c.apply_atomic_snapshot(|c| {
+            c.0 += 1;
+            Ok::<_, ()>(())
+        });
+
+        assert_eq!(c, Counter(2));
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn unwrap_panic_location() {
+        use std::{env, process::Command};
+
+        // The panic is raised in a child process, as catching its location
+        // in this process would require replacing the panic hook, which is
+        // shared with the tests running in parallel.
+        const CHILD: &str = "SHPAT_UNWRAP_PANIC_LOCATION";
+
+        let expected_line = line!() + 2;
+        if env::var_os(CHILD).is_some() {
+            Vec::<()>::new().apply_unwrap(Vec::pop);
+        }
+
+        let (_, module) = module_path!().split_once("::").unwrap();
+        let output = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "--nocapture", "--test-threads=1"])
+            .arg(format!("{}::unwrap_panic_location", module))
+            .env(CHILD, "1")
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(
+            stderr.contains(&format!("apply.rs:{}:", expected_line)),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn try_success_path() {
+        let left: Result<_, std::num::ParseIntError> = Vec::new()
+            .apply_try(|v| "42".parse().map(|n| v.push(n)))
+            .and_then(|v| v.apply_try(|v| "101".parse().map(|n| v.push(n))));
+
+        assert_eq!(left, Ok(vec![42, 101]));
+    }
+
+    #[test]
+    fn try_failure_path() {
+        let left = vec![1]
+            .apply_try(Vec::pop)
+            .and_then(|v| v.apply_try(Vec::pop));
+
+        assert_eq!(left, None);
+    }
+
+    #[test]
+    fn conditional_application() {
+        let left = HashMap::new()
+            .apply_if(true, |m| m.insert("foo", 101))
+            .apply_if(false, |m| m.insert("bar", 42))
+            .apply_if_some(Some(1969), |m, v| m.insert("baz", v))
+            .apply_if_some(None, |m, v| m.insert("qux", v))
+            .apply_when(|m| m.contains_key("foo"), |m| m.insert("quux", 0))
+            .apply_when(HashMap::is_empty, |m| m.insert("corge", 1));
+
+        let right = HashMap::new()
+            .apply(|m| m.insert("foo", 101))
+            .apply(|m| m.insert("baz", 1969))
+            .apply(|m| m.insert("quux", 0));
+
+        assert_eq!(left, right);
+    }
+}
+
+#[cfg(test)]
+mod apply_mut {
+    use super::*;
+
+    use std::{collections::HashMap, fmt::Write};
+
+    #[test]
+    fn build_hashmap_in_place() {
+        let mut left = HashMap::with_capacity(3);
+        left.apply_mut(|m| m.insert("foo", 101))
+            .apply_mut(|m| m.insert("bar", 42))
+            .apply_mut_unwrap(|m| m.insert("foo", 1969));
+
+        let right = HashMap::new()
+            .apply(|m| m.insert("foo", 1969))
+            .apply(|m| m.insert("bar", 42));
+
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn trait_object() {
+        let mut s = String::new();
+        let w: &mut dyn Write = &mut s;
+
+        w.apply_mut_unwrap(|w| w.write_str("foo"))
+            .apply_mut_unwrap(|w| w.write_char('!'));
+
+        assert_eq!(s, "foo!");
+    }
+
+    #[test]
+    #[should_panic]
+    fn unwrap_panic_path() {
+        Vec::<()>::new().apply_mut_unwrap(Vec::pop);
+    }
 }
@@ -0,0 +1,231 @@
+//! Attaching human-readable context to failures.
+//!
+//! When unwrapping a `Result` fails, the panic message only contains the
+//! error, which often lacks information about what the program was trying to
+//! do. The `Context` trait allows to wrap the failure of any `Fallible` in a
+//! `ContextError`, along with a message. Context can be stacked, and is
+//! displayed from the outermost to the innermost:
+//!
+//! ```rust
+//! use shpat::prelude::*;
+//!
+//! fn parse_port(s: &str) -> Result<u16, ContextError> {
+//!     s.parse::<u16>().context("parsing port")
+//! }
+//!
+//! let err = parse_port("80a").context("loading config").unwrap_err();
+//!
+//! assert_eq!(
+//!     err.to_string(),
+//!     "loading config: parsing port: invalid digit found in string",
+//! );
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+use crate::unwrappable::Fallible;
+
+/// A failure, along with a message describing what was being done when it
+/// happened.
+///
+/// It is displayed as the context, followed by the failure. Its `Debug`
+/// implementation does the same, so that panics raised when unwrapping a
+/// `Result<T, ContextError>` show the whole context chain.
+pub struct ContextError {
+    /// What was being done when the failure happened.
+    context: String,
+    /// The failure.
+    source: Box<dyn Error + Send + Sync + 'static>,
+}
+
+impl ContextError {
+    /// Creates an error from a failure and its context.
+    ///
+    /// The failure can be any `Error`, or a message, such as a `String`.
+    pub fn new<C, E>(context: C, source: E) -> ContextError
+    where
+        C: Display,
+        E: Into<Box<dyn Error + Send + Sync + 'static>>,
+    {
+        ContextError {
+            context: context.to_string(),
+            source: source.into(),
+        }
+    }
+
+    /// Returns the context attached to the failure.
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    /// Returns the outermost context, followed by the context of the inner
+    /// `ContextError`s, if any.
+    pub fn chain(&self) -> Vec<&str> {
+        let mut chain = vec![self.context()];
+        let mut source = self.source.downcast_ref::<ContextError>();
+
+        while let Some(err) = source {
+            chain.push(err.context());
+            source = err.source.downcast_ref::<ContextError>();
+        }
+
+        chain
+    }
+}
+
+impl Display for ContextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl Debug for ContextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// Allows to attach context to the failure of a `Fallible`.
+///
+/// It is implemented for every `Fallible` whose failure can be converted into
+/// a `Box<dyn Error + Send + Sync>`, which includes:
+///   - `Option`, whose failure is `NoneError`,
+///   - `Poll`, whose failure is `PendingError`,
+///   - `Result<T, E>` and `ControlFlow<E, T>`, where `E` is an `Error`, a
+///     `String`, a `&str` or a `Box<dyn Error + Send + Sync>`,
+///   - `Result<T, ContextError>`, so that context can be stacked.
+///
+/// Failures which are neither an `Error` nor a message, such as the `()` in
+/// `Result<T, ()>`, can not be given context.
+///
+/// # Example
+///
+/// ```should_panic
+/// use std::collections::HashMap;
+///
+/// use shpat::prelude::*;
+///
+/// let config: HashMap<&str, &str> = HashMap::new();
+///
+/// // Panics with "loading config: reading port: value is `None`".
+/// let port = config
+///     .get("port")
+///     .context("reading port")
+///     .context("loading config")
+///     .unwrap();
+/// ```
+pub trait Context: Fallible {
+    /// Wraps the failure of `self`, if any, in a `ContextError` described
+    /// by `context`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ContextError` if `self` is a failure.
+    fn context<C: Display>(self, context: C) -> Result<Self::Success, ContextError>;
+
+    /// Same as `context`, but the context is computed only in case of
+    /// failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ContextError` if `self` is a failure.
+    fn with_context<C: Display, F: FnOnce() -> C>(
+        self,
+        f: F,
+    ) -> Result<Self::Success, ContextError>;
+}
+
+impl<T> Context for T
+where
+    T: Fallible,
+    T::Failure: Into<Box<dyn Error + Send + Sync + 'static>>,
+{
+    fn context<C: Display>(self, context: C) -> Result<T::Success, ContextError> {
+        Fallible::into_result(self).map_err(|e| ContextError::new(context, e))
+    }
+
+    fn with_context<C: Display, F: FnOnce() -> C>(self, f: F) -> Result<T::Success, ContextError> {
+        Fallible::into_result(self).map_err(|e| ContextError::new(f(), e))
+    }
+}
+
+#[cfg(test)]
+mod context_chain {
+    use super::*;
+
+    use std::{ops::ControlFlow, task::Poll};
+
+    use crate::unwrappable::Unwrappable;
+
+    #[test]
+    fn display_chain() {
+        let err = "80a"
+            .parse::<u16>()
+            .context("parsing port")
+            .context("loading config")
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "loading config: parsing port: invalid digit found in string"
+        );
+        assert_eq!(err.chain(), ["loading config", "parsing port"]);
+        assert!(err.source().unwrap().is::<ContextError>());
+    }
+
+    #[test]
+    fn option() {
+        let err = None::<()>
+            .with_context(|| format!("reading {}", "port"))
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "reading port: value is `None`");
+    }
+
+    #[test]
+    fn message_failures() {
+        let r: Result<(), String> = Err(String::from("invalid digit"));
+        let err = r.context("parsing port").unwrap_err();
+        assert_eq!(err.to_string(), "parsing port: invalid digit");
+
+        let r: Result<(), Box<dyn Error + Send + Sync>> = Err("no such file".into());
+        let err = r.context("opening config").unwrap_err();
+        assert_eq!(err.to_string(), "opening config: no such file");
+    }
+
+    #[test]
+    fn poll_and_control_flow() {
+        let err = Poll::<()>::Pending.context("reading socket").unwrap_err();
+        assert_eq!(err.to_string(), "reading socket: value is `Pending`");
+
+        let c: ControlFlow<&str, ()> = ControlFlow::Break("limit reached");
+        let err = c.context("walking tree").unwrap_err();
+        assert_eq!(err.to_string(), "walking tree: limit reached");
+    }
+
+    #[test]
+    fn success_is_untouched() {
+        assert_eq!(Some(42).context("unused").unwrap(), 42);
+        assert_eq!("42".parse::<u8>().context("unused").unwrap(), 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "loading config: parsing port: invalid digit")]
+    fn unwrap_panic_message() {
+        let r = "80a"
+            .parse::<u16>()
+            .context("parsing port")
+            .context("loading config");
+
+        Unwrappable::unwrap(r);
+    }
+}
@@ -0,0 +1,247 @@
+//! Amortizing the destruction of big collections over time.
+//!
+//! Dropping a collection with millions of elements takes time, during which
+//! the current thread is blocked. `QuickDrop` solves this by dropping it in
+//! another thread, which is not always possible, or desirable.
+//!
+//! The `DropQueue` allows to destroy collections a few elements at a time
+//! instead. Collections are pushed to the queue, and each call to `tick`
+//! drops at most a given number of elements. This way, the cost of the
+//! destruction can be spread over the frames of a game loop, or the
+//! iterations of an event loop.
+//!
+//! ```rust
+//! use shpat::prelude::*;
+//!
+//! let mut queue = DropQueue::new();
+//! queue.push(vec![String::new(); 1000]);
+//!
+//! // In the main loop:
+//! while !queue.is_empty() {
+//!     // Do some work...
+//!
+//!     queue.tick(100);
+//! }
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::{self, Debug, Formatter},
+    iter::Fuse,
+};
+
+/// A collection which can be pushed to a `DropQueue`.
+///
+/// The queue tears a collection down by consuming its owning iterator. This
+/// trait is implemented for collections whose owning iterator only yields the
+/// elements they already hold, so that `tick` does nothing but drop them. An
+/// arbitrary iterator, such as `0..` or `iter.map(expensive)`, may never end,
+/// or may do any amount of work in `tick`.
+///
+/// It can be implemented for other collections which uphold this property.
+///
+/// ```compile_fail
+/// use shpat::prelude::*;
+///
+/// let mut queue = DropQueue::new();
+/// queue.push(0..);
+/// ```
+pub trait Drainable: IntoIterator + 'static {}
+
+impl<T: 'static> Drainable for Vec<T> {}
+
+impl<T: 'static> Drainable for VecDeque<T> {}
+
+impl<K: 'static, V: 'static, S: 'static> Drainable for HashMap<K, V, S> {}
+
+impl<T: 'static, S: 'static> Drainable for HashSet<T, S> {}
+
+impl<K: 'static, V: 'static> Drainable for BTreeMap<K, V> {}
+
+impl<T: 'static> Drainable for BTreeSet<T> {}
+
+/// A collection which can be torn down a few elements at a time.
+trait Teardown {
+    /// Drops at most `budget` elements, and returns how many elements were
+    /// dropped.
+    fn drop_some(&mut self, budget: usize) -> usize;
+
+    /// Returns `true` if every element has been dropped.
+    fn is_exhausted(&mut self) -> bool;
+}
+
+/// Tears a collection down by consuming its owning iterator.
+struct Draining<I: Iterator> {
+    /// The owning iterator of the collection.
+    iter: Fuse<I>,
+    /// The element pulled by `is_exhausted`, if any.
+    peeked: Option<I::Item>,
+}
+
+impl<I: Iterator> Teardown for Draining<I> {
+    fn drop_some(&mut self, budget: usize) -> usize {
+        let mut dropped = 0;
+
+        if budget > 0 && self.peeked.take().is_some() {
+            dropped += 1;
+        }
+
+        while dropped < budget && self.iter.next().is_some() {
+            dropped += 1;
+        }
+
+        dropped
+    }
+
+    fn is_exhausted(&mut self) -> bool {
+        if self.peeked.is_none() {
+            self.peeked = self.iter.next();
+        }
+
+        self.peeked.is_none()
+    }
+}
+
+/// A queue of collections, which are destroyed incrementally.
+///
+/// Any `Drainable` collection can be pushed, such as `Vec`, `VecDeque`,
+/// `HashMap` or `BTreeMap`. Each call to `tick` drops at most the given
+/// number of elements, starting with the collections which were pushed first.
+///
+/// The elements themselves are dropped in one go: a `Vec<Vec<String>>` is
+/// torn down one `Vec<String>` at a time.
+///
+/// Collections which are still in the queue when it is dropped are dropped
+/// immediately.
+#[derive(Default)]
+pub struct DropQueue {
+    /// The collections waiting to be destroyed, in the order in which they
+    /// were pushed.
+    pending: VecDeque<Box<dyn Teardown>>,
+}
+
+impl DropQueue {
+    /// Creates an empty queue.
+    pub fn new() -> DropQueue {
+        DropQueue::default()
+    }
+
+    /// Pushes `collection` at the end of the queue.
+    pub fn push<C>(&mut self, collection: C)
+    where
+        C: Drainable,
+        C::IntoIter: 'static,
+    {
+        self.pending.push_back(Box::new(Draining {
+            iter: collection.into_iter().fuse(),
+            peeked: None,
+        }));
+    }
+
+    /// Drops at most `budget` elements, and returns how many elements were
+    /// dropped.
+    ///
+    /// The memory of a collection is freed once all its elements have been
+    /// dropped.
+    ///
+    /// ```rust
+    /// use shpat::prelude::*;
+    ///
+    /// let mut queue = DropQueue::new();
+    /// queue.push(vec![1, 2, 3]);
+    /// queue.push(vec![4, 5]);
+    ///
+    /// assert_eq!(queue.tick(4), 4);
+    /// assert_eq!(queue.len(), 1);
+    /// assert_eq!(queue.tick(4), 1);
+    /// assert!(queue.is_empty());
+    /// ```
+    pub fn tick(&mut self, budget: usize) -> usize {
+        let mut dropped = 0;
+
+        while let Some(front) = self.pending.front_mut() {
+            dropped += front.drop_some(budget - dropped);
+
+            if !front.is_exhausted() {
+                break;
+            }
+
+            self.pending.pop_front();
+        }
+
+        dropped
+    }
+
+    /// Returns the number of collections which have not been completely
+    /// destroyed yet.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if every collection pushed to the queue has been
+    /// destroyed.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl Debug for DropQueue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DropQueue")
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tick {
+    use super::*;
+
+    use std::{
+        collections::{BTreeMap, HashMap},
+        rc::Rc,
+    };
+
+    #[test]
+    fn respects_budget() {
+        let witness = Rc::new(());
+        let mut queue = DropQueue::new();
+        queue.push(vec![Rc::clone(&witness); 10]);
+
+        assert_eq!(queue.tick(3), 3);
+        assert_eq!(Rc::strong_count(&witness), 8);
+
+        assert_eq!(queue.tick(0), 0);
+        assert_eq!(Rc::strong_count(&witness), 8);
+
+        assert_eq!(queue.tick(100), 7);
+        assert_eq!(Rc::strong_count(&witness), 1);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn several_collections() {
+        let mut queue = DropQueue::new();
+        queue.push((0..5).map(|i| (i, i)).collect::<HashMap<_, _>>());
+        queue.push((0..5).map(|i| (i, i)).collect::<BTreeMap<_, _>>());
+        queue.push(Vec::<()>::new());
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.tick(5), 5);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.tick(6), 5);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn remaining_collections_are_dropped() {
+        let witness = Rc::new(());
+        let mut queue = DropQueue::new();
+        queue.push(vec![Rc::clone(&witness); 10]);
+        queue.tick(2);
+
+        drop(queue);
+
+        assert_eq!(Rc::strong_count(&witness), 1);
+    }
+}
@@ -32,6 +32,31 @@
 //! hash map, but want to keep returned value), and `apply_unwrap`, which will
 //! call `unwrap` on every `Unwrapable` returned value.
 //!
+//! Its non-panicking counterpart, `apply_try`, wraps the object in the returned
+//! `Option` or `Result`, so that the chain can be continued with `?`.
+//!
+//! Every call to `apply` moves the object. The `ApplyMut` trait provides the same
+//! methods, prefixed with `apply_mut`, which take and return a mutable reference
+//! instead. They modify the object in place and work on unsized types too.
+//!
+//! ## `tap` and `pipe`
+//!
+//! The `Tap` trait allows to inspect a value in the middle of a method chain,
+//! for instance to log it. `tap_dbg` does the same, but only in debug builds.
+//! The `Pipe` trait allows to pass a value to any function, and to continue
+//! the chain with whatever it returns.
+//!
+//! ```rust
+//! use shpat::prelude::*;
+//!
+//! let len = vec![3, 1, 2]
+//!     .apply(|v| v.sort())
+//!     .tap(|v| println!("sorted: {:?}", v))
+//!     .pipe(|v| v.len());
+//!
+//! assert_eq!(len, 3);
+//! ```
+//!
 //! ## `quick_drop`
 //!
 //! As shown by [Aaron Abramov](https://github.com/aaronabramov/) in [their
@@ -40,7 +65,9 @@
 //! suggested is to run the `drop` function in a new thread.
 //!
 //! The `QuickDrop` trait is provided, which allows, for most objects, to
-//! be dropped in a new thread.
+//! be dropped in a background thread. Rather than spawning a thread for each
+//! object, a single background thread is started the first time `quick_drop`
+//! is called, and drops every object it receives.
 //!
 //! ```rust
 //! use shpat::prelude::*;
@@ -53,9 +80,15 @@
 //! heavy.quick_drop();
 //! ```
 //!
+//! The `QuickDropBox` wrapper calls `quick_drop` on its content when it is
+//! dropped, so that it can not be forgotten on early returns.
+//!
+//! When no other thread can be used, the `DropQueue` allows to destroy big
+//! collections incrementally, a bounded number of elements at a time.
+//!
 //! ### Traits required by `QuickDrop`
 //!
-//! The object on which `quick_drop` is called is moved to a new thread. As
+//! The object on which `quick_drop` is called is moved to another thread. As
 //! such, it has to be `Send`. Additionaly, as `quick_drop` takes ownership of
 //! it, the object has to be `Sized`.
 //!
@@ -67,15 +100,47 @@
 //! if it was a failure. These behaviours are unified with the `unwrap`
 //! function.
 //!
-//! This trait is implemented for both `Result` and `Option`. It is closely
-//! related to the `Try` trait from the standard library.
+//! This trait is implemented for `Result`, `Option`, `Poll` and `ControlFlow`.
+//! The `Result` implementation also covers `LockResult` and `thread::Result`.
+//! The documentation of `Unwrappable` says which variant of each type is a
+//! failure. It is closely related to the `Try` trait from the standard library.
+//!
+//! There is no direct implementation for `Poll<Result<T, E>>`, which unwraps
+//! into a `Result<T, E>`. Both layers can be unwrapped at once with
+//! `UnwrapAll`.
+//!
+//! Besides `unwrap`, it provides `expect`, `unwrap_or`, `unwrap_or_else`,
+//! `unwrap_or_default` and `is_success`, so that generic code can handle any
+//! success or failure type uniformly.
+//!
+//! The `Context` trait attaches a human-readable message to a failure, which
+//! is shown along with the error when it is displayed or unwrapped:
+//!
+//! ```rust
+//! use shpat::prelude::*;
+//!
+//! let err = "80a"
+//!     .parse::<u16>()
+//!     .context("parsing port")
+//!     .context("loading config")
+//!     .unwrap_err();
+//!
+//! assert_eq!(
+//!     err.to_string(),
+//!     "loading config: parsing port: invalid digit found in string",
+//! );
+//! ```
 
 #![forbid(missing_docs)]
 #![forbid(clippy::missing_docs_in_private_items)]
 #![forbid(clippy::missing_errors_doc)]
 
 mod apply;
-mod quick_drop;
+mod context;
+mod drop_queue;
+mod pipe;
+pub mod quick_drop;
+mod tap;
 mod unwrappable;
 
 pub mod prelude;
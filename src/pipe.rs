@@ -0,0 +1,129 @@
+//! Transforming a value in the middle of a method chain.
+//!
+//! Free functions and functions which take their argument in a non-`self`
+//! position break method chaining. The `Pipe` trait allows to pass a value to
+//! any function, and to continue the chain with its return value:
+//!
+//! ```rust
+//! use shpat::prelude::*;
+//!
+//! let len = vec![1, 2, 3]
+//!     .apply(|v| v.push(4))
+//!     .pipe(|v| v.into_iter().sum::<u32>())
+//!     .pipe(|sum| sum.to_string())
+//!     .pipe_ref(String::len);
+//!
+//! assert_eq!(len, 2);
+//! ```
+
+use crate::unwrappable::Unwrappable;
+
+/// Allows to pass a value to a function in a method chain.
+pub trait Pipe {
+    /// Passes `self` to `f`, returning the value returned by `f`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let s = 42.pipe(|n| format!("{}!", n));
+    ///
+    /// assert_eq!(s, "42!");
+    /// ```
+    fn pipe<R, F: FnOnce(Self) -> R>(self, f: F) -> R
+    where
+        Self: Sized,
+    {
+        f(self)
+    }
+
+    /// Passes a reference to `self` to `f`, returning the value returned by
+    /// `f`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let first = "hello world".pipe_ref(|s| s.split(' ').next());
+    ///
+    /// assert_eq!(first, Some("hello"));
+    /// ```
+    fn pipe_ref<'a, R, F: FnOnce(&'a Self) -> R>(&'a self, f: F) -> R {
+        f(self)
+    }
+
+    /// Passes a mutable reference to `self` to `f`, returning the value
+    /// returned by `f`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let mut v = vec![1, 2, 3];
+    /// let last = v.pipe_mut(Vec::pop);
+    ///
+    /// assert_eq!(last, Some(3));
+    /// assert_eq!(v, [1, 2]);
+    /// ```
+    fn pipe_mut<'a, R, F: FnOnce(&'a mut Self) -> R>(&'a mut self, f: F) -> R {
+        f(self)
+    }
+
+    /// Passes `self` to `f`, unwrapping the value returned by `f`.
+    ///
+    /// # Panics
+    ///
+    /// This method is guaranteed to panic if `f` returns a panickable value.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let n: u32 = "42".pipe_unwrap(str::parse);
+    ///
+    /// assert_eq!(n, 42);
+    /// ```
+    #[track_caller]
+    fn pipe_unwrap<T, U: Unwrappable<T>, F: FnOnce(Self) -> U>(self, f: F) -> T
+    where
+        Self: Sized,
+    {
+        Unwrappable::unwrap(f(self))
+    }
+}
+
+// Automatic implementation of the `Pipe` trait for any type, including unsized
+// ones.
+impl<T: ?Sized> Pipe for T {}
+
+#[cfg(test)]
+mod transformation {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::apply::Apply;
+
+    #[test]
+    fn pipe_to_other_type() {
+        let keys = HashMap::new()
+            .apply(|m| m.insert("foo", 101))
+            .apply(|m| m.insert("bar", 42))
+            .pipe(|m| m.into_keys().collect::<Vec<_>>())
+            .apply(|v| v.sort());
+
+        assert_eq!(keys, ["bar", "foo"]);
+    }
+
+    #[test]
+    fn pipe_unsized() {
+        let mut v = vec![3, 1, 2];
+
+        v.as_mut_slice().pipe_mut(<[_]>::sort);
+        let max = v.as_slice().pipe_ref(|s| s.last().copied());
+
+        assert_eq!(max, Some(3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unwrap_panic_path() {
+        "not a number".pipe_unwrap(str::parse::<u32>);
+    }
+}
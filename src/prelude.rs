@@ -1,4 +1,14 @@
 //! The prelude file, importing this module will bring every pattern to the
 //! scope.
 
-pub use crate::{apply::Apply, unwrappable::Unwrappable, quick_drop::QuickDrop};
+pub use crate::{
+    apply::{Apply, ApplyMut, Snapshot},
+    context::{Context, ContextError},
+    drop_queue::{Drainable, DropQueue},
+    pipe::Pipe,
+    quick_drop::{
+        DropCost, ParallelDrop, QuickDrop, QuickDropBox, QuickDropRuntime, QuickDropShared,
+    },
+    tap::Tap,
+    unwrappable::{Fallible, Innermost, Layer, NoneError, PendingError, UnwrapAll, Unwrappable},
+};
@@ -1,92 +1,2646 @@
-//! A trait for dropping heavy objects in a new thread.
+//! A trait for dropping heavy objects in a background thread.
+//!
+//! Every object passed to `quick_drop` is sent to a single, process-wide,
+//! thread, called the reaper, which drops the objects it receives one after
+//! the other. The reaper is started the first time `quick_drop` is called.
+//!
+//! Spawning a new thread for each object would be simpler, but spawning a
+//! thread is often more expensive than dropping a medium-sized object, and
+//! may exhaust the thread limit of the process when called in a loop. See the
+//! `quick_drop` benchmark, which can be run with `cargo bench`.
+//!
+//! # Small objects
+//!
+//! Sending an object to another thread has a cost, which is not worth paying
+//! for objects which are cheap to drop. Objects which do not need to be
+//! dropped at all, such as integers, are always dropped in the current thread.
+//!
+//! Types which implement `DropCost` can report how much memory they free
+//! when dropped. When passed to `quick_drop_measured`, they are dropped in
+//! the current thread if this cost is lower than a threshold, which can be
+//! changed with `set_inline_threshold`.
+//!
+//! # Waiting for background drops
+//!
+//! Each object sent to the reaper is given a ticket, which is released once
+//! the object has been dropped. This allows to wait for a specific object to
+//! be dropped, with the `DropHandle` returned by `quick_drop_handle`, or for
+//! a batch of objects, with `flush` and `wait_idle`:
+//!
+//! ```rust
+//! use shpat::{prelude::*, quick_drop};
+//!
+//! vec![0u8; 1024].quick_drop();
+//! String::from("hello").quick_drop_handle().wait();
+//!
+//! quick_drop::flush();
+//! ```
+//!
+//! # Borrowed objects
+//!
+//! `quick_drop` requires objects to be `'static`, as the reaper may outlive
+//! them. Objects which borrow data from the current stack frame can be
+//! dropped in the background inside a `scope`, which waits for them to be
+//! dropped before returning:
+//!
+//! ```rust
+//! use shpat::quick_drop;
+//!
+//! let text = String::from("a long text to parse");
+//! let words: Vec<&str> = text.split(' ').collect();
+//!
+//! quick_drop::scope(|s| {
+//!     s.quick_drop(words);
+//! });
+//! ```
+//!
+//! # Parallel drops
+//!
+//! A single thread may still need a long time to drop a collection with
+//! hundreds of millions of elements. `ParallelDrop::quick_drop_parallel`
+//! splits a collection into chunks in the background, and drops them in a
+//! small pool of worker threads. The number of threads can be changed with
+//! `set_parallelism`.
+//!
+//! # Backpressure
+//!
+//! By default, any number of objects can wait to be dropped in the
+//! background. If objects are passed to `quick_drop` faster than the reaper
+//! drops them, then the memory used by the program grows without bound. A
+//! capacity can be set with `set_capacity`, along with the behavior of
+//! `quick_drop` when the capacity is reached, with `set_backpressure`.
+//!
+//! # Custom executors
+//!
+//! Programs which already own a thread pool may not want an additional
+//! thread to be started. A `DropExecutor` can be installed with
+//! `set_executor`, or passed to `quick_drop_on`, in which case objects are
+//! handed to it instead of the reaper.
+//!
+//! Two features still start threads of their own, as the executor can only
+//! run `'static` jobs which must not wait for each other:
+//! `quick_drop_parallel` drops the chunks of a collection in worker threads,
+//! and `scope` spawns a thread for the duration of the scope.
+//!
+//! # Testing
+//!
+//! Destructors which run in the background make it hard to check their side
+//! effects in tests. `inline_drops` returns a guard which, while it is alive,
+//! makes `quick_drop` drop objects in the current thread, so that tests do
+//! not have to change the code calling `quick_drop`:
+//!
+//! ```rust
+//! use shpat::{prelude::*, quick_drop};
+//!
+//! let _inline = quick_drop::inline_drops();
+//!
+//! // Dropped right now, in the current thread.
+//! vec![String::new(); 16].quick_drop();
+//! ```
+//!
+//! # Panics in destructors
+//!
+//! A panic raised while dropping an object in the background does not stop
+//! the reaper. It is caught, counted (see `panic_count`) and passed to the
+//! hook installed with `set_panic_hook`, if any. With
+//! `set_repanic_on_flush(true)`, the next call to `flush` or `wait_idle`
+//! panics as well, so that bugs in destructors are not silently ignored.
+//!
+//! # Statistics
+//!
+//! `stats` returns a snapshot of the work done by the reaper: the number of
+//! objects waiting to be dropped, the number of objects dropped so far, and
+//! the time spent in their destructors. A breakdown per type can be enabled
+//! with `set_per_type_stats`.
 
-use std::thread;
+use std::{
+    any::{self, Any},
+    cell::Cell,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SendError, Sender},
+        Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-/// A trait for dropping heavy objects in a new thread.
+/// An object waiting to be dropped by the reaper.
+type Garbage = Box<dyn Send>;
+
+/// An object sent to the reaper, along with its ticket.
+struct Job {
+    /// The ticket to release once `garbage` is dropped.
+    ticket: u64,
+    /// The name of the type of `garbage`.
+    type_name: &'static str,
+    /// The object to drop.
+    garbage: Garbage,
+}
+
+impl Job {
+    /// Drops the object, then releases its ticket.
+    ///
+    /// If dropping the object panics, then the panic is caught and reported.
+    fn run(self) {
+        let Job {
+            ticket,
+            type_name,
+            garbage,
+        } = self;
+
+        let in_background = IN_BACKGROUND.with(|b| b.replace(true));
+        let start = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(move || drop(garbage)));
+        let elapsed = start.elapsed();
+        IN_BACKGROUND.with(|b| b.set(in_background));
+
+        // Released even if reporting the panic panics, so that `flush` does
+        // not wait for this ticket forever.
+        let _release = Release {
+            ticket,
+            type_name,
+            elapsed,
+        };
+
+        if let Err(payload) = result {
+            report(DropPanic::new(type_name, payload));
+        }
+    }
+}
+
+/// Releases the ticket of a dropped object, and records its statistics, when
+/// dropped.
+struct Release {
+    /// The ticket of the dropped object.
+    ticket: u64,
+    /// The name of the type of the dropped object.
+    type_name: &'static str,
+    /// The time spent dropping the object.
+    elapsed: Duration,
+}
+
+impl Drop for Release {
+    fn drop(&mut self) {
+        {
+            let mut tickets = tickets();
+            tickets.pending.remove(&self.ticket);
+            tickets.completed += 1;
+            tickets.time_in_drop += self.elapsed;
+
+            if PER_TYPE_STATS.load(Ordering::Relaxed) {
+                let type_name = self.type_name;
+                tickets
+                    .per_type
+                    .entry(type_name)
+                    .or_insert_with(|| TypeStats::new(type_name))
+                    .record(self.elapsed);
+            }
+        }
+        RELEASED.notify_all();
+    }
+}
+
+/// An object waiting to be dropped, handed to a `DropExecutor`.
 ///
-/// This trait is inspired by [a blog
-/// post](https://abramov.io/rust-dropping-things-in-another-thread) written by
-/// [Aaron Abramov](https://github.com/aaronabramov/), in which they show that
-/// running `drop` may slow down a program.
+/// Running the job drops the object, and updates the statistics and the
+/// pending objects waited for by `flush`. If the job is dropped without being
+/// run, then it is run in the current thread.
+#[derive(Debug)]
+pub struct DropJob {
+    /// The job to run. It is `None` only once it has been run.
+    job: Option<Job>,
+}
+
+impl DropJob {
+    /// Drops the object in the current thread.
+    ///
+    /// Panics raised while dropping the object are caught and reported as if
+    /// the object was dropped by the reaper.
+    pub fn run(mut self) {
+        if let Some(job) = self.job.take() {
+            job.run();
+        }
+    }
+
+    /// Returns the name of the type of the object.
+    pub fn type_name(&self) -> &'static str {
+        self.job.as_ref().map_or("", |job| job.type_name)
+    }
+}
+
+impl Drop for DropJob {
+    fn drop(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.run();
+        }
+    }
+}
+
+impl Debug for Job {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Job")
+            .field("ticket", &self.ticket)
+            .field("type_name", &self.type_name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs the jobs created by `quick_drop`, instead of the reaper thread.
+///
+/// It is implemented for any `Fn(DropJob)`, so that a closure can be used to
+/// hand the jobs to an existing thread pool.
 ///
 /// # Example
 ///
 /// ```rust
-/// use shpat::prelude::*;
+/// use std::thread;
 ///
-/// // An object whose drop is likely to take a long time
-/// struct Heavy;
+/// use shpat::{prelude::*, quick_drop::{self, DropJob}};
 ///
-/// let heavy = Heavy;
+/// quick_drop::set_executor(|job: DropJob| {
+///     thread::spawn(move || job.run());
+/// });
 ///
-/// heavy.quick_drop();
+/// vec![String::new(); 1024].quick_drop();
+/// quick_drop::flush();
 /// ```
-pub trait QuickDrop: Sized + Send + 'static {
-    /// Drops an object in a newly spawned thread.
-    fn quick_drop(self) {
-        thread::spawn(move || drop(self));
+pub trait DropExecutor: Send + Sync {
+    /// Runs `job`, usually in another thread.
+    fn execute(&self, job: DropJob);
+}
+
+impl<F: Fn(DropJob) + Send + Sync> DropExecutor for F {
+    fn execute(&self, job: DropJob) {
+        self(job)
     }
 }
 
-impl<T: Sized + Send + 'static> QuickDrop for T {}
+/// The executor installed with `set_executor`, if any.
+static EXECUTOR: RwLock<Option<Arc<dyn DropExecutor>>> = RwLock::new(None);
 
-#[cfg(test)]
-mod simple_object {
-    use super::*;
+/// Makes `quick_drop` hand the objects to `executor`, instead of sending them
+/// to the reaper thread.
+///
+/// It replaces the previously installed executor, if any.
+///
+/// The chunks of the collections passed to `quick_drop_parallel`, and the
+/// objects dropped in a `scope`, are not handed to `executor`: the former
+/// must be waited for by the job dropping the collection, which may deadlock
+/// an executor with a single thread, and the latter are not `'static`.
+pub fn set_executor<E: DropExecutor + 'static>(executor: E) {
+    *EXECUTOR.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(executor));
+}
 
-    #[allow(dead_code)]
-    struct S {
-        a: u8,
-        b: u32,
-        c: char,
+/// Removes the executor installed with `set_executor`, so that `quick_drop`
+/// sends the objects to the reaper thread again.
+pub fn reset_executor() {
+    *EXECUTOR.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// A snapshot of the work done by the reaper, returned by `stats`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct DropStats {
+    /// The number of objects waiting to be dropped.
+    pub pending: usize,
+    /// The number of objects dropped in the background since the start of
+    /// the program.
+    pub completed: u64,
+    /// The number of panics raised while dropping objects in the
+    /// background.
+    pub panicked: usize,
+    /// The total time spent in the destructors of the objects dropped in the
+    /// background.
+    pub time_in_drop: Duration,
+    /// The statistics of each type of dropped object, sorted from the type
+    /// whose destructors took the longest total time to the shortest.
+    ///
+    /// It is empty unless `set_per_type_stats(true)` was called.
+    pub per_type: Vec<TypeStats>,
+}
+
+/// The statistics of the objects of a given type dropped in the background.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TypeStats {
+    /// The name of the type, as returned by `std::any::type_name`.
+    pub type_name: &'static str,
+    /// The number of objects of this type dropped in the background.
+    pub count: u64,
+    /// The total time spent in the destructors of these objects.
+    pub total_time: Duration,
+    /// The longest time spent in the destructor of one of these objects.
+    pub max_time: Duration,
+}
+
+impl TypeStats {
+    /// Creates empty statistics for `type_name`.
+    fn new(type_name: &'static str) -> TypeStats {
+        TypeStats {
+            type_name,
+            count: 0,
+            total_time: Duration::ZERO,
+            max_time: Duration::ZERO,
+        }
     }
 
-    #[test]
-    fn quick_drop() {
-        let s = S {
-            a: 42,
-            b: 101,
-            c: '_',
-        };
+    /// Records that an object of this type was dropped in `elapsed`.
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total_time += elapsed;
+        self.max_time = self.max_time.max(elapsed);
+    }
+}
 
-        s.quick_drop();
+/// Whether statistics should be recorded for each type of dropped object.
+static PER_TYPE_STATS: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether statistics should be recorded for each type of object
+/// dropped in the background. It is disabled by default.
+///
+/// Disabling it discards the statistics recorded so far.
+pub fn set_per_type_stats(enabled: bool) {
+    PER_TYPE_STATS.store(enabled, Ordering::Relaxed);
+
+    if !enabled {
+        tickets().per_type.clear();
     }
 }
 
-#[cfg(test)]
-mod with_generic_type {
-    use super::*;
+/// Returns a snapshot of the work done by the reaper.
+///
+/// ```rust
+/// use shpat::{prelude::*, quick_drop};
+///
+/// vec![String::new(); 1024].quick_drop_handle().wait();
+///
+/// let stats = quick_drop::stats();
+/// assert!(stats.completed >= 1);
+/// ```
+pub fn stats() -> DropStats {
+    let (pending, completed, time_in_drop, mut per_type) = {
+        let tickets = tickets();
+        (
+            tickets.pending.len(),
+            tickets.completed,
+            tickets.time_in_drop,
+            tickets.per_type.values().cloned().collect::<Vec<_>>(),
+        )
+    };
 
-    #[allow(dead_code)]
-    struct S<T> {
-        val: T,
+    per_type.sort_by_key(|stats| Reverse(stats.total_time));
+
+    DropStats {
+        pending,
+        completed,
+        panicked: panic_count(),
+        time_in_drop,
+        per_type,
     }
+}
 
-    #[test]
-    fn quick_drop() {
-        let s = S {
-            val: String::from("foo"),
+/// A panic raised while dropping an object in the background.
+#[derive(Clone, Debug)]
+pub struct DropPanic {
+    /// The name of the type of the dropped object.
+    type_name: &'static str,
+    /// The message of the panic.
+    message: String,
+}
+
+impl DropPanic {
+    /// Creates a `DropPanic` from the payload of a caught panic.
+    fn new(type_name: &'static str, payload: Box<dyn Any + Send>) -> DropPanic {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => String::from(*message),
+                Err(_) => String::from("Box<dyn Any>"),
+            },
         };
 
-        s.quick_drop();
+        DropPanic { type_name, message }
+    }
+
+    /// Returns the name of the type of the object whose destructor panicked.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the message of the panic.
+    pub fn message(&self) -> &str {
+        &self.message
     }
 }
 
-#[cfg(test)]
-mod with_static_lifetime {
-    use super::*;
+impl Display for DropPanic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dropping a `{}` panicked: {}",
+            self.type_name, self.message
+        )
+    }
+}
 
-    #[allow(dead_code)]
-    struct S<'a> {
-        val: &'a usize,
+impl Error for DropPanic {}
+
+/// A function called each time dropping an object in the background panics.
+pub type PanicHook = Box<dyn Fn(&DropPanic) + Send + Sync>;
+
+/// The hook installed with `set_panic_hook`.
+static PANIC_HOOK: RwLock<Option<PanicHook>> = RwLock::new(None);
+
+/// The number of panics raised while dropping objects in the background.
+static PANIC_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether `flush` and `wait_idle` should panic if a background drop
+/// panicked.
+static REPANIC_ON_FLUSH: AtomicBool = AtomicBool::new(false);
+
+/// The panics which have not been raised again by `flush` or `wait_idle`
+/// yet. Panics are stored only if `REPANIC_ON_FLUSH` is set.
+static UNREPORTED: Mutex<Vec<DropPanic>> = Mutex::new(Vec::new());
+
+/// Counts `panic`, passes it to the panic hook, and stores it so that it can
+/// be raised again by `flush`.
+fn report(panic: DropPanic) {
+    PANIC_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    if let Some(hook) = PANIC_HOOK
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        // A panicking hook must not kill the thread dropping the objects.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| hook(&panic)));
     }
 
-    #[test]
-    fn quick_drop() {
-        let s: S<'static> = S {
-            val: &42,
-        };
+    if REPANIC_ON_FLUSH.load(Ordering::Relaxed) {
+        UNREPORTED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(panic);
+    }
+}
 
-        s.quick_drop();
+/// Panics with the oldest panic which has not been raised again yet, if any
+/// and if `REPANIC_ON_FLUSH` is set.
+fn repanic() {
+    if !REPANIC_ON_FLUSH.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let panic = {
+        let mut unreported = UNREPORTED.lock().unwrap_or_else(PoisonError::into_inner);
+        if unreported.is_empty() {
+            return;
+        }
+        unreported.remove(0)
+    };
+
+    panic!("{}", panic);
+}
+
+/// Installs a hook, which is called each time dropping an object in the
+/// background panics. It replaces the previously installed hook, if any.
+///
+/// The hook is called in the thread which dropped the object.
+///
+/// ```rust
+/// use shpat::{prelude::*, quick_drop};
+///
+/// quick_drop::set_panic_hook(|panic| {
+///     eprintln!("{}", panic);
+/// });
+/// ```
+pub fn set_panic_hook<F: Fn(&DropPanic) + Send + Sync + 'static>(hook: F) {
+    *PANIC_HOOK.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(hook));
+}
+
+/// Removes the hook installed with `set_panic_hook`, and returns it.
+pub fn take_panic_hook() -> Option<PanicHook> {
+    PANIC_HOOK
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+}
+
+/// Returns the number of panics raised while dropping objects in the
+/// background since the start of the program.
+pub fn panic_count() -> usize {
+    PANIC_COUNT.load(Ordering::Relaxed)
+}
+
+/// Sets whether `flush` and `wait_idle` should panic if dropping an object in
+/// the background panicked since their last call. It is disabled by default.
+///
+/// Each background panic is raised again at most once.
+pub fn set_repanic_on_flush(repanic: bool) {
+    REPANIC_ON_FLUSH.store(repanic, Ordering::Relaxed);
+
+    if !repanic {
+        UNREPORTED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+/// Keeps track of the objects which have been sent to the reaper, but not
+/// dropped yet, and of the objects which have been dropped.
+struct Tickets {
+    /// The ticket which will be given to the next object.
+    next: u64,
+    /// The tickets of the objects which have not been dropped yet.
+    pending: BTreeSet<u64>,
+    /// The number of objects which have been dropped.
+    completed: u64,
+    /// The time spent dropping objects.
+    time_in_drop: Duration,
+    /// The statistics of each type of dropped object, if enabled.
+    per_type: BTreeMap<&'static str, TypeStats>,
+}
+
+/// The tickets of every object passed to `quick_drop`.
+static TICKETS: Mutex<Tickets> = Mutex::new(Tickets {
+    next: 0,
+    pending: BTreeSet::new(),
+    completed: 0,
+    time_in_drop: Duration::ZERO,
+    per_type: BTreeMap::new(),
+});
+
+/// Notified each time a ticket is released.
+static RELEASED: Condvar = Condvar::new();
+
+/// Locks the ticket registry.
+///
+/// The registry is always left in a consistent state, so a poisoned lock is
+/// simply recovered.
+fn tickets() -> MutexGuard<'static, Tickets> {
+    TICKETS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Blocks the current thread until `done` returns `true`.
+fn wait_until<F: FnMut(&Tickets) -> bool>(mut done: F) {
+    let guard = tickets();
+    drop(
+        RELEASED
+            .wait_while(guard, |tickets| !done(tickets))
+            .unwrap_or_else(PoisonError::into_inner),
+    );
+}
+
+/// Blocks the current thread until `done` returns `true`, or until `timeout`
+/// has elapsed.
+///
+/// Returns `false` if the timeout elapsed.
+fn wait_until_timeout<F: FnMut(&Tickets) -> bool>(timeout: Duration, mut done: F) -> bool {
+    let guard = tickets();
+    let (_guard, result) = RELEASED
+        .wait_timeout_while(guard, timeout, |tickets| !done(tickets))
+        .unwrap_or_else(PoisonError::into_inner);
+
+    !result.timed_out()
+}
+
+/// The channel connected to the reaper thread, or `None` if the reaper could
+/// not be started.
+static REAPER: OnceLock<Option<Sender<Job>>> = OnceLock::new();
+
+/// Returns the channel connected to the reaper thread, starting it if needed.
+fn reaper() -> Option<&'static Sender<Job>> {
+    REAPER
+        .get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Job>();
+
+            thread::Builder::new()
+                .name(String::from("shpat-reaper"))
+                .spawn(move || receiver.into_iter().for_each(Job::run))
+                .ok()
+                .map(|_| sender)
+        })
+        .as_ref()
+}
+
+/// What `quick_drop` does when the number of objects waiting to be dropped
+/// in the background reaches the capacity set with `set_capacity`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Blocks the current thread until an object has been dropped by the
+    /// reaper. This is the default.
+    ///
+    /// Objects passed to `quick_drop` by a destructor running in the
+    /// background are dropped inline instead, as blocking the reaper would
+    /// never end.
+    ///
+    /// This deadlocks with a `DropExecutor` which queues the jobs to run them
+    /// later in the thread calling `quick_drop`, such as an event loop: the
+    /// thread blocks until a job is run, and the jobs are only run by this
+    /// thread.
+    Block,
+    /// Drops the object in the current thread.
+    Inline,
+    /// Sends the object to the reaper anyway, ignoring the capacity.
+    Grow,
+}
+
+impl Backpressure {
+    /// Returns the representation of `self` stored in `BACKPRESSURE`.
+    fn to_u8(self) -> u8 {
+        match self {
+            Backpressure::Block => 0,
+            Backpressure::Inline => 1,
+            Backpressure::Grow => 2,
+        }
+    }
+
+    /// Returns the policy stored in `BACKPRESSURE` as `value`.
+    fn from_u8(value: u8) -> Backpressure {
+        match value {
+            0 => Backpressure::Block,
+            1 => Backpressure::Inline,
+            _ => Backpressure::Grow,
+        }
+    }
+}
+
+/// The maximum number of objects waiting to be dropped in the background,
+/// or `usize::MAX` if it is unbounded.
+static CAPACITY: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// What to do when `CAPACITY` is reached, as returned by
+/// `Backpressure::to_u8`.
+static BACKPRESSURE: AtomicU8 = AtomicU8::new(0);
+
+thread_local! {
+    /// Whether the current thread is one of the threads dropping objects in
+    /// the background.
+    static IN_BACKGROUND: Cell<bool> = const { Cell::new(false) };
+
+    /// Whether objects passed to `quick_drop` by the current thread should be
+    /// dropped inline.
+    static INLINE_DROPS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets the maximum number of objects waiting to be dropped in the
+/// background, or removes the limit if `capacity` is `None`. There is no
+/// limit by default.
+///
+/// What happens when the limit is reached is set with `set_backpressure`.
+/// With a capacity of `0`, `Backpressure::Block` behaves like
+/// `Backpressure::Inline`, as there is never any room to wait for.
+///
+/// ```rust
+/// use shpat::quick_drop::{self, Backpressure};
+///
+/// quick_drop::set_capacity(Some(1024));
+/// quick_drop::set_backpressure(Backpressure::Inline);
+/// ```
+pub fn set_capacity(capacity: Option<usize>) {
+    CAPACITY.store(capacity.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Returns the maximum number of objects waiting to be dropped in the
+/// background, or `None` if it is unbounded.
+pub fn capacity() -> Option<usize> {
+    match CAPACITY.load(Ordering::Relaxed) {
+        usize::MAX => None,
+        capacity => Some(capacity),
+    }
+}
+
+/// Sets what `quick_drop` does when the capacity set with `set_capacity` is
+/// reached.
+pub fn set_backpressure(policy: Backpressure) {
+    BACKPRESSURE.store(policy.to_u8(), Ordering::Relaxed);
+}
+
+/// Returns what `quick_drop` does when the capacity set with `set_capacity`
+/// is reached.
+pub fn backpressure() -> Backpressure {
+    Backpressure::from_u8(BACKPRESSURE.load(Ordering::Relaxed))
+}
+
+/// Gives a ticket to a new object, waiting for some room in the queue if
+/// needed.
+///
+/// Returns `None` if the object should be dropped in the current thread
+/// instead, because of the backpressure policy.
+fn take_ticket() -> Option<u64> {
+    let capacity = CAPACITY.load(Ordering::Relaxed);
+    let mut tickets = tickets();
+
+    if tickets.pending.len() >= capacity {
+        match backpressure() {
+            Backpressure::Grow => {}
+            Backpressure::Inline => return None,
+            // Nothing can be released if nothing is pending, so blocking
+            // would never end.
+            Backpressure::Block if capacity == 0 => return None,
+            Backpressure::Block if IN_BACKGROUND.with(Cell::get) => return None,
+            Backpressure::Block => {
+                tickets = RELEASED
+                    .wait_while(tickets, |tickets| tickets.pending.len() >= capacity)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+    }
+
+    let ticket = tickets.next;
+    tickets.next += 1;
+    tickets.pending.insert(ticket);
+    Some(ticket)
+}
+
+/// Sends `value` to `executor`, or to the executor installed with
+/// `set_executor`, or to the reaper, and returns its ticket.
+///
+/// If the reaper thread can not be started, or if the backpressure policy
+/// says so, then the object is dropped in the current thread, and `None` is
+/// returned.
+fn send<T: Send + 'static>(value: T, executor: Option<&dyn DropExecutor>) -> Option<u64> {
+    let ticket = match take_ticket() {
+        Some(ticket) => ticket,
+        None => {
+            drop(value);
+            return None;
+        }
+    };
+
+    let job = Job {
+        ticket,
+        type_name: any::type_name::<T>(),
+        garbage: Box::new(value),
+    };
+
+    if let Some(executor) = executor {
+        executor.execute(DropJob { job: Some(job) });
+        return Some(ticket);
+    }
+
+    // The lock is released before running the executor, which may call
+    // `set_executor`.
+    let executor = EXECUTOR
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    if let Some(executor) = executor {
+        executor.execute(DropJob { job: Some(job) });
+        return Some(ticket);
+    }
+
+    match reaper() {
+        Some(sender) => {
+            if let Err(SendError(job)) = sender.send(job) {
+                job.run();
+            }
+        }
+        None => job.run(),
+    }
+
+    Some(ticket)
+}
+
+/// Drops `value` in the current thread if `inline` is `true`, or sends it to
+/// the background otherwise.
+///
+/// Returns the ticket of `value` if it was sent to the background.
+fn dispose<T: Send + 'static>(value: T, inline: bool) -> Option<u64> {
+    dispose_on(value, inline, None)
+}
+
+/// Same as `dispose`, but sends `value` to `executor` if it is not `None`.
+fn dispose_on<T: Send + 'static>(
+    value: T,
+    inline: bool,
+    executor: Option<&dyn DropExecutor>,
+) -> Option<u64> {
+    if inline || INLINE_DROPS.with(Cell::get) {
+        drop(value);
+        None
+    } else {
+        send(value, executor)
+    }
+}
+
+/// Sets whether objects passed to `quick_drop` by the current thread should
+/// be dropped in the current thread, rather than in the background.
+///
+/// This only affects the current thread. It is mostly useful in tests, to
+/// check the side effects of destructors. Consider using `inline_drops`,
+/// which restores the previous setting automatically.
+pub fn set_inline_drops(inline: bool) {
+    INLINE_DROPS.with(|i| i.set(inline));
+}
+
+/// Returns `true` if objects passed to `quick_drop` by the current thread are
+/// dropped in the current thread.
+pub fn inline_drops_enabled() -> bool {
+    INLINE_DROPS.with(Cell::get)
+}
+
+/// Makes `quick_drop` drop objects in the current thread, until the returned
+/// guard is dropped.
+///
+/// This only affects the current thread.
+///
+/// ```rust
+/// use std::sync::{
+///     atomic::{AtomicBool, Ordering},
+///     Arc,
+/// };
+///
+/// use shpat::{prelude::*, quick_drop};
+///
+/// struct Flagged(Arc<AtomicBool>);
+///
+/// impl Drop for Flagged {
+///     fn drop(&mut self) {
+///         self.0.store(true, Ordering::SeqCst);
+///     }
+/// }
+///
+/// let flag = Arc::new(AtomicBool::new(false));
+/// let _inline = quick_drop::inline_drops();
+///
+/// Flagged(Arc::clone(&flag)).quick_drop();
+///
+/// assert!(flag.load(Ordering::SeqCst));
+/// ```
+pub fn inline_drops() -> InlineDrops {
+    let previous = inline_drops_enabled();
+    set_inline_drops(true);
+
+    InlineDrops {
+        previous,
+        _not_send: PhantomData,
+    }
+}
+
+/// A guard returned by `inline_drops`, which makes `quick_drop` drop objects
+/// in the current thread while it is alive.
+#[derive(Debug)]
+#[must_use = "objects are dropped inline only while the guard is alive"]
+pub struct InlineDrops {
+    /// The setting to restore when the guard is dropped.
+    previous: bool,
+    /// The guard changes a thread-local setting, so it must not be sent to
+    /// another thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for InlineDrops {
+    fn drop(&mut self) {
+        set_inline_drops(self.previous);
+    }
+}
+
+/// The default value of the inline threshold, in bytes.
+const DEFAULT_INLINE_THRESHOLD: usize = 4096;
+
+/// The cost under which `quick_drop_measured` drops objects in the current
+/// thread.
+static INLINE_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_INLINE_THRESHOLD);
+
+/// Sets the cost, in bytes, under which `quick_drop_measured` drops objects
+/// in the current thread. It defaults to 4096 bytes.
+///
+/// Setting it to `0` makes `quick_drop_measured` send every object which
+/// needs to be dropped to the background thread.
+pub fn set_inline_threshold(bytes: usize) {
+    INLINE_THRESHOLD.store(bytes, Ordering::Relaxed);
+}
+
+/// Returns the cost, in bytes, under which `quick_drop_measured` drops
+/// objects in the current thread.
+pub fn inline_threshold() -> usize {
+    INLINE_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Reports how much memory is freed when an object is dropped.
+///
+/// This is used by `QuickDrop::quick_drop_measured` to decide whether an
+/// object is worth being dropped in the background. The reported value is an
+/// approximation, which should be cheap to compute: the implementations
+/// provided for collections only measure the memory of the collection itself.
+/// As the elements may own heap memory of their own, a collection which holds
+/// elements needing to be dropped, such as a `Vec<String>`, reports a cost of
+/// `usize::MAX` unless it is empty, so that it is always dropped in the
+/// background.
+///
+/// # Example
+///
+/// ```rust
+/// use shpat::prelude::*;
+///
+/// struct Image {
+///     pixels: Vec<u32>,
+/// }
+///
+/// impl DropCost for Image {
+///     fn drop_cost(&self) -> usize {
+///         self.pixels.drop_cost()
+///     }
+/// }
+///
+/// // Small enough to be dropped in the current thread.
+/// Image { pixels: vec![0; 4] }.quick_drop_measured();
+///
+/// // Dropped in the background.
+/// Image { pixels: vec![0; 1 << 20] }.quick_drop_measured();
+/// ```
+pub trait DropCost {
+    /// Returns an approximation of the heap memory, in bytes, which is freed
+    /// when `self` is dropped.
+    fn drop_cost(&self) -> usize;
+}
+
+impl DropCost for String {
+    fn drop_cost(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T> DropCost for Vec<T> {
+    fn drop_cost(&self) -> usize {
+        collection_cost::<T>(self.len(), self.capacity())
+    }
+}
+
+impl<T> DropCost for VecDeque<T> {
+    fn drop_cost(&self) -> usize {
+        collection_cost::<T>(self.len(), self.capacity())
+    }
+}
+
+impl<K, V, S> DropCost for HashMap<K, V, S> {
+    fn drop_cost(&self) -> usize {
+        collection_cost::<(K, V)>(self.len(), self.capacity())
+    }
+}
+
+impl<T, S> DropCost for HashSet<T, S> {
+    fn drop_cost(&self) -> usize {
+        collection_cost::<T>(self.len(), self.capacity())
+    }
+}
+
+impl<K, V> DropCost for BTreeMap<K, V> {
+    fn drop_cost(&self) -> usize {
+        collection_cost::<(K, V)>(self.len(), self.len())
+    }
+}
+
+impl<T> DropCost for BTreeSet<T> {
+    fn drop_cost(&self) -> usize {
+        collection_cost::<T>(self.len(), self.len())
+    }
+}
+
+/// Returns the cost of a collection holding `len` elements of type `T`, in a
+/// buffer which can hold `capacity` elements.
+///
+/// The memory owned by the elements can not be measured cheaply, so the cost
+/// is `usize::MAX` if any element needs to be dropped.
+fn collection_cost<T>(len: usize, capacity: usize) -> usize {
+    if mem::needs_drop::<T>() && len > 0 {
+        usize::MAX
+    } else {
+        capacity.saturating_mul(mem::size_of::<T>())
+    }
+}
+
+impl<T: DropCost> DropCost for Box<T> {
+    fn drop_cost(&self) -> usize {
+        mem::size_of::<T>().saturating_add((**self).drop_cost())
+    }
+}
+
+/// Blocks the current thread until every object passed to `quick_drop` before
+/// this call has been dropped.
+///
+/// Objects sent to the reaper while `flush` is waiting are not waited for.
+///
+/// # Panics
+///
+/// If `set_repanic_on_flush(true)` was called, this function panics if
+/// dropping an object in the background panicked since the last call to
+/// `flush` or `wait_idle`.
+pub fn flush() {
+    let last = tickets().next;
+    wait_until(|tickets| tickets.pending.first().is_none_or(|&t| t >= last));
+    repanic();
+}
+
+/// Blocks the current thread until no object is waiting to be dropped in the
+/// background.
+///
+/// Unlike `flush`, this also waits for objects sent to the reaper while
+/// waiting. As such, it may never return if other threads keep calling
+/// `quick_drop`.
+///
+/// # Panics
+///
+/// If `set_repanic_on_flush(true)` was called, this function panics if
+/// dropping an object in the background panicked since the last call to
+/// `flush` or `wait_idle`.
+pub fn wait_idle() {
+    wait_until(|tickets| tickets.pending.is_empty());
+    repanic();
+}
+
+/// Blocks the current thread until no object is waiting to be dropped in the
+/// background, or until `timeout` has elapsed.
+///
+/// Returns `false` if the timeout elapsed before every object was dropped.
+///
+/// # Panics
+///
+/// If `set_repanic_on_flush(true)` was called, this function panics if
+/// dropping an object in the background panicked since the last call to
+/// `flush` or `wait_idle`.
+pub fn wait_idle_timeout(timeout: Duration) -> bool {
+    let idle = wait_until_timeout(timeout, |tickets| tickets.pending.is_empty());
+    repanic();
+    idle
+}
+
+/// A guard which waits for every background drop to complete when it is
+/// dropped.
+///
+/// The reaper thread is killed when `main` returns, so objects still waiting
+/// to be dropped at this point are never dropped. This is a problem for
+/// objects whose destructor flushes a file, or releases a lock. Holding a
+/// `QuickDropRuntime` in `main` ensures that every object passed to
+/// `quick_drop` is dropped before the program exits, including when `main`
+/// returns early or panics.
+///
+/// Unlike `wait_idle`, dropping a `QuickDropRuntime` never panics, even if
+/// `set_repanic_on_flush(true)` was called.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use shpat::prelude::*;
+///
+/// let _runtime = QuickDropRuntime::with_timeout(Duration::from_secs(5));
+///
+/// vec![String::new(); 1024].quick_drop();
+///
+/// // The vector is guaranteed to be dropped before the end of the program,
+/// // unless dropping it takes more than five seconds.
+/// ```
+#[derive(Debug, Default)]
+#[must_use = "the background drops are waited for when the runtime is dropped"]
+pub struct QuickDropRuntime {
+    /// The maximum time to wait for, or `None` to wait as long as needed.
+    timeout: Option<Duration>,
+}
+
+impl QuickDropRuntime {
+    /// Creates a guard which waits, when dropped, until every background drop
+    /// has completed.
+    pub fn new() -> QuickDropRuntime {
+        QuickDropRuntime { timeout: None }
+    }
+
+    /// Creates a guard which waits, when dropped, until every background drop
+    /// has completed, or until `timeout` has elapsed.
+    pub fn with_timeout(timeout: Duration) -> QuickDropRuntime {
+        QuickDropRuntime {
+            timeout: Some(timeout),
+        }
+    }
+}
+
+impl Drop for QuickDropRuntime {
+    fn drop(&mut self) {
+        let idle = |tickets: &Tickets| tickets.pending.is_empty();
+
+        match self.timeout {
+            Some(timeout) => {
+                wait_until_timeout(timeout, idle);
+            }
+            None => wait_until(idle),
+        }
+    }
+}
+
+/// A handle to an object passed to `quick_drop_handle`, which allows to wait
+/// until it is dropped.
+#[derive(Debug)]
+#[must_use = "use `quick_drop` if the handle is not needed"]
+pub struct DropHandle {
+    /// The ticket of the object, or `None` if it was dropped in the current
+    /// thread.
+    ticket: Option<u64>,
+}
+
+impl DropHandle {
+    /// Returns `true` if the object has already been dropped.
+    pub fn is_dropped(&self) -> bool {
+        self.ticket
+            .is_none_or(|ticket| !tickets().pending.contains(&ticket))
+    }
+
+    /// Blocks the current thread until the object is dropped.
+    pub fn wait(self) {
+        if let Some(ticket) = self.ticket {
+            wait_until(|tickets| !tickets.pending.contains(&ticket));
+        }
+    }
+}
+
+/// A trait for dropping heavy objects in a background thread.
+///
+/// This trait is inspired by [a blog
+/// post](https://abramov.io/rust-dropping-things-in-another-thread) written by
+/// [Aaron Abramov](https://github.com/aaronabramov/), in which they show that
+/// running `drop` may slow down a program.
+///
+/// # Example
+///
+/// ```rust
+/// use shpat::prelude::*;
+///
+/// // An object whose drop is likely to take a long time
+/// struct Heavy;
+///
+/// let heavy = Heavy;
+///
+/// heavy.quick_drop();
+/// ```
+pub trait QuickDrop: Sized + Send + 'static {
+    /// Drops an object in the background reaper thread.
+    ///
+    /// If the object does not need to be dropped, or if the reaper thread can
+    /// not be started, then the object is dropped in the current thread.
+    ///
+    /// Otherwise, the object is sent to the background, however cheap it is
+    /// to drop: an empty `Vec` is sent as well. Use `quick_drop_measured` to
+    /// drop cheap objects in the current thread.
+    fn quick_drop(self) {
+        dispose(self, !mem::needs_drop::<Self>());
+    }
+
+    /// Drops an object in the background reaper thread, unless it is cheap
+    /// enough to be dropped in the current thread.
+    ///
+    /// The cost of dropping `self` is the sum of its size and of the value
+    /// returned by `drop_cost`. If it is lower than `inline_threshold`, then
+    /// `self` is dropped in the current thread.
+    ///
+    /// ```rust
+    /// use shpat::prelude::*;
+    ///
+    /// // Dropped in the current thread.
+    /// Vec::<u8>::new().quick_drop_measured();
+    ///
+    /// // Dropped in the background.
+    /// vec![0u8; 1 << 20].quick_drop_measured();
+    /// ```
+    fn quick_drop_measured(self)
+    where
+        Self: DropCost,
+    {
+        let cost = mem::size_of::<Self>().saturating_add(self.drop_cost());
+        dispose(
+            self,
+            !mem::needs_drop::<Self>() || cost < inline_threshold(),
+        );
+    }
+
+    /// Drops an object in the background reaper thread, and returns a handle
+    /// which allows to wait until it is dropped.
+    ///
+    /// ```rust
+    /// use shpat::prelude::*;
+    ///
+    /// let handle = vec![0u8; 1024].quick_drop_handle();
+    /// handle.wait();
+    /// ```
+    fn quick_drop_handle(self) -> DropHandle {
+        DropHandle {
+            ticket: dispose(self, !mem::needs_drop::<Self>()),
+        }
+    }
+
+    /// Hands an object to `executor`, which drops it.
+    ///
+    /// This ignores the executor installed with `set_executor`. As with
+    /// `quick_drop`, objects which do not need to be dropped are dropped in
+    /// the current thread.
+    ///
+    /// ```rust
+    /// use std::thread;
+    ///
+    /// use shpat::{prelude::*, quick_drop::DropJob};
+    ///
+    /// let executor = |job: DropJob| {
+    ///     thread::spawn(move || job.run());
+    /// };
+    ///
+    /// vec![String::new(); 1024].quick_drop_on(&executor);
+    /// ```
+    fn quick_drop_on(self, executor: &dyn DropExecutor) {
+        dispose_on(self, !mem::needs_drop::<Self>(), Some(executor));
+    }
+
+    /// Hands an object to `executor`, and returns a handle which allows to
+    /// wait until it is dropped.
+    ///
+    /// ```rust
+    /// use shpat::{prelude::*, quick_drop::DropJob};
+    ///
+    /// let handle = vec![String::new(); 1024].quick_drop_on_handle(&DropJob::run);
+    /// assert!(handle.is_dropped());
+    /// ```
+    fn quick_drop_on_handle(self, executor: &dyn DropExecutor) -> DropHandle {
+        DropHandle {
+            ticket: dispose_on(self, !mem::needs_drop::<Self>(), Some(executor)),
+        }
+    }
+}
+
+impl<T: Sized + Send + 'static> QuickDrop for T {}
+
+/// The number of threads used by `quick_drop_parallel`, or `0` to use the
+/// available parallelism.
+static PARALLELISM: AtomicUsize = AtomicUsize::new(0);
+
+/// Collections with fewer elements than this are not split by
+/// `quick_drop_parallel`.
+const MIN_CHUNK_LEN: usize = 1024;
+
+/// Sets the number of threads used to drop a collection passed to
+/// `quick_drop_parallel`.
+///
+/// By default, or if `threads` is `0`, the value returned by
+/// `std::thread::available_parallelism` is used.
+pub fn set_parallelism(threads: usize) {
+    PARALLELISM.store(threads, Ordering::Relaxed);
+}
+
+/// Returns the number of threads used to drop a collection passed to
+/// `quick_drop_parallel`.
+pub fn parallelism() -> usize {
+    /// The value returned by `available_parallelism`, which may be costly to
+    /// compute, as it reads the cgroup settings on Linux.
+    static AVAILABLE: OnceLock<usize> = OnceLock::new();
+
+    match PARALLELISM.load(Ordering::Relaxed) {
+        0 => *AVAILABLE.get_or_init(|| thread::available_parallelism().map_or(1, Into::into)),
+        threads => threads,
+    }
+}
+
+/// A chunk of a collection, sent to the drop workers. It reports whether
+/// dropping the chunk panicked.
+type Chunk = Box<dyn FnOnce() + Send>;
+
+/// The threads dropping the chunks of the collections passed to
+/// `quick_drop_parallel`.
+struct Workers {
+    /// The channel connected to the workers.
+    sender: Sender<Chunk>,
+    /// The channel from which the workers receive chunks.
+    receiver: Arc<Mutex<Receiver<Chunk>>>,
+    /// The number of workers started so far.
+    count: usize,
+}
+
+/// The number of chunks sent to the drop workers so far.
+#[cfg(test)]
+static CHUNKS_SENT: AtomicUsize = AtomicUsize::new(0);
+
+/// The drop workers, which are started the first time they are needed.
+static WORKERS: Mutex<Option<Workers>> = Mutex::new(None);
+
+thread_local! {
+    /// Whether the current thread is a drop worker.
+    static IS_DROP_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Returns the channel connected to the drop workers, starting workers until
+/// there are at least `count` of them.
+///
+/// Returns `None` if no worker could be started.
+fn workers(count: usize) -> Option<Sender<Chunk>> {
+    let mut workers = WORKERS.lock().unwrap_or_else(PoisonError::into_inner);
+    let workers = workers.get_or_insert_with(|| {
+        let (sender, receiver) = mpsc::channel();
+        Workers {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+            count: 0,
+        }
+    });
+
+    while workers.count < count {
+        let receiver = Arc::clone(&workers.receiver);
+        let spawned = thread::Builder::new()
+            .name(String::from("shpat-drop-worker"))
+            .spawn(move || {
+                IN_BACKGROUND.with(|b| b.set(true));
+                IS_DROP_WORKER.with(|w| w.set(true));
+
+                loop {
+                    let next = receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .recv();
+                    match next {
+                        Ok(chunk) => chunk(),
+                        Err(_) => return,
+                    }
+                }
+            });
+
+        if spawned.is_err() {
+            break;
+        }
+        workers.count += 1;
+    }
+
+    (workers.count > 0).then(|| workers.sender.clone())
+}
+
+/// A collection which is split into chunks, dropped in parallel by the drop
+/// workers, when it is dropped.
+///
+/// The collection is converted into a `Vec` when it is dropped, so that the
+/// conversion does not happen in the thread calling `quick_drop_parallel`.
+struct Chunked<T: Send + 'static, C: Into<Vec<T>>> {
+    /// The collection to drop. It is `None` only once it has been dropped.
+    collection: Option<C>,
+    /// The type of the elements of the collection.
+    _elements: PhantomData<T>,
+}
+
+impl<T: Send + 'static, C: Into<Vec<T>>> Chunked<T, C> {
+    /// Wraps `collection`.
+    fn new(collection: C) -> Chunked<T, C> {
+        Chunked {
+            collection: Some(collection),
+            _elements: PhantomData,
+        }
+    }
+}
+
+impl<T: Send + 'static, C: Into<Vec<T>>> Drop for Chunked<T, C> {
+    fn drop(&mut self) {
+        let mut elements: Vec<T> = match self.collection.take() {
+            Some(collection) => collection.into(),
+            None => return,
+        };
+
+        // Freeing the buffer at once is faster than copying it into chunks.
+        if !mem::needs_drop::<T>() {
+            return;
+        }
+
+        // A worker waiting for other workers may wait forever.
+        if IS_DROP_WORKER.with(Cell::get) {
+            return;
+        }
+
+        let threads = parallelism().max(1);
+        let chunk_len = elements.len().div_ceil(threads).max(MIN_CHUNK_LEN);
+        if elements.len() <= chunk_len {
+            return;
+        }
+
+        let sender = match workers(threads - 1) {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let (done, finished) = mpsc::channel();
+        let mut sent = 0;
+        while elements.len() > chunk_len {
+            let chunk = elements.split_off(elements.len() - chunk_len);
+            let done = done.clone();
+            let job: Chunk = Box::new(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(move || drop(chunk)));
+                let _ = done.send(result.err());
+            });
+
+            // If the workers are gone, then the chunk is dropped in the
+            // current thread.
+            if sender.send(job).is_ok() {
+                sent += 1;
+                #[cfg(test)]
+                CHUNKS_SENT.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        // The chunks are waited for even if dropping the remaining elements
+        // panics, so that the ticket of the collection is not released early.
+        let remaining = panic::catch_unwind(AssertUnwindSafe(move || drop(elements)));
+
+        // Panics are raised again once every chunk is dropped, so that they
+        // are reported as if the collection was dropped in the current thread.
+        let panics: Vec<_> = remaining
+            .err()
+            .into_iter()
+            .chain(finished.iter().take(sent).flatten())
+            .collect();
+        if let Some(payload) = panics.into_iter().next() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// A trait for dropping big collections in several background threads.
+///
+/// The collection is sent to the reaper thread, which splits it into chunks,
+/// and hands them to a pool of worker threads. The pool is started the first
+/// time it is needed, and grown when `parallelism` increases. Collections
+/// with fewer than a thousand elements are not split.
+///
+/// The reaper waits for every chunk to be dropped, so `flush`, `wait_idle` and
+/// `DropHandle` behave as with `QuickDrop`.
+///
+/// # Example
+///
+/// ```rust
+/// use shpat::{prelude::*, quick_drop};
+///
+/// let big: Vec<Vec<String>> = vec![vec![String::from("foo"); 16]; 100_000];
+///
+/// big.quick_drop_parallel();
+/// quick_drop::flush();
+/// ```
+pub trait ParallelDrop: QuickDrop {
+    /// Drops a collection in several background threads.
+    fn quick_drop_parallel(self);
+}
+
+impl<T: Send + 'static> ParallelDrop for Vec<T> {
+    fn quick_drop_parallel(self) {
+        Chunked::new(self).quick_drop();
+    }
+}
+
+impl<T: Send + 'static> ParallelDrop for VecDeque<T> {
+    fn quick_drop_parallel(self) {
+        Chunked::new(self).quick_drop();
+    }
+}
+
+/// An object waiting to be dropped in a `DropScope`, along with the name of
+/// its type.
+type ScopedGarbage<'env> = (&'static str, Box<dyn Send + 'env>);
+
+/// A scope in which objects borrowing from the enclosing stack frame can be
+/// dropped in the background.
+///
+/// It is created by the `scope` function.
+pub struct DropScope<'env> {
+    /// The channel connected to the thread dropping the objects of this
+    /// scope.
+    sender: Sender<ScopedGarbage<'env>>,
+}
+
+impl<'env> DropScope<'env> {
+    /// Drops `value` in the background. It is guaranteed to be dropped before
+    /// `scope` returns.
+    ///
+    /// As with `QuickDrop::quick_drop`, objects which do not need to be
+    /// dropped are dropped in the current thread.
+    pub fn quick_drop<T: Send + 'env>(&self, value: T) {
+        if !mem::needs_drop::<T>() {
+            return;
+        }
+
+        let garbage: ScopedGarbage<'env> = (any::type_name::<T>(), Box::new(value));
+        if let Err(SendError(garbage)) = self.sender.send(garbage) {
+            drop(garbage);
+        }
+    }
+}
+
+/// Drops every object received by `receiver`, reporting the panics raised
+/// while dropping them.
+fn drop_all_scoped(receiver: Receiver<ScopedGarbage<'_>>) {
+    for (type_name, garbage) in receiver {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(move || drop(garbage))) {
+            report(DropPanic::new(type_name, payload));
+        }
+    }
+}
+
+/// Creates a scope in which objects which are not `'static` can be dropped
+/// in the background.
+///
+/// A thread is spawned for the duration of the scope. Every object passed to
+/// `DropScope::quick_drop` is dropped in this thread, and `scope` waits for
+/// all of them to be dropped before returning. This is modelled on
+/// `std::thread::scope`.
+///
+/// If the thread can not be spawned, then the objects are dropped in the
+/// current thread.
+///
+/// # Example
+///
+/// ```rust
+/// use shpat::quick_drop;
+///
+/// let buffer = vec![0u8; 1024];
+/// let chunks: Vec<&[u8]> = buffer.chunks(16).collect();
+///
+/// let len = quick_drop::scope(|s| {
+///     let len = chunks.len();
+///     s.quick_drop(chunks);
+///     len
+/// });
+///
+/// assert_eq!(len, 64);
+/// ```
+pub fn scope<'env, R, F: FnOnce(&DropScope<'env>) -> R>(f: F) -> R {
+    thread::scope(|s| {
+        let (sender, receiver) = mpsc::channel();
+
+        // If the thread can not be spawned, then the receiver is dropped, and
+        // `DropScope::quick_drop` drops objects in the current thread.
+        let _ = thread::Builder::new()
+            .name(String::from("shpat-scoped-reaper"))
+            .spawn_scoped(s, move || drop_all_scoped(receiver));
+
+        f(&DropScope { sender })
+    })
+}
+
+/// A trait for dropping shared pointers, which sends the shared object to the
+/// background reaper thread only if it is dropped.
+///
+/// Calling `quick_drop` on an `Arc` sends it to the reaper, even if the
+/// reaper only has to decrement its reference count. `quick_drop_last`
+/// decrements the reference count in the current thread instead, and sends
+/// the shared object to the reaper only if this was its last reference.
+///
+/// It is implemented for `Rc<T>` and `Arc<T>`, for which the last reference
+/// is detected atomically, and for `Arc<str>`, `Arc<[T]>` and
+/// `Arc<dyn Any + Send + Sync>`. The shared object of the latter can not be
+/// moved out of its allocation, so the last reference is detected from the
+/// reference count, which is best-effort: if the other references are
+/// dropped concurrently, then the shared object may be dropped in the current
+/// thread.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use shpat::prelude::*;
+///
+/// let shared = Arc::new(vec![0u8; 1 << 20]);
+/// let other = Arc::clone(&shared);
+///
+/// // Only decrements the reference count.
+/// shared.quick_drop_last();
+///
+/// // Drops the `Vec` in the background.
+/// other.quick_drop_last();
+/// ```
+pub trait QuickDropShared {
+    /// Drops this reference, sending the shared object to the reaper if it
+    /// was the last reference to it.
+    fn quick_drop_last(self);
+}
+
+impl<T: Send + 'static> QuickDropShared for Arc<T> {
+    fn quick_drop_last(self) {
+        if let Some(inner) = Arc::into_inner(self) {
+            inner.quick_drop();
+        }
+    }
+}
+
+impl QuickDropShared for Arc<str> {
+    fn quick_drop_last(self) {
+        quick_drop_last_unsized(self);
+    }
+}
+
+impl<T: Send + Sync + 'static> QuickDropShared for Arc<[T]> {
+    fn quick_drop_last(self) {
+        quick_drop_last_unsized(self);
+    }
+}
+
+impl QuickDropShared for Arc<dyn Any + Send + Sync> {
+    fn quick_drop_last(self) {
+        quick_drop_last_unsized(self);
+    }
+}
+
+/// Sends `arc` to the reaper if it looks like the last reference to the
+/// shared object, which can not be moved out of its allocation as it is
+/// unsized.
+///
+/// This is best-effort: if the other references are dropped concurrently,
+/// then each thread may see another reference alive, and the shared object
+/// may be dropped in the current thread.
+fn quick_drop_last_unsized<T: ?Sized + Send + Sync + 'static>(arc: Arc<T>) {
+    if Arc::strong_count(&arc) == 1 {
+        arc.quick_drop();
+    }
+}
+
+impl<T: Send + 'static> QuickDropShared for Rc<T> {
+    fn quick_drop_last(self) {
+        if let Some(inner) = Rc::into_inner(self) {
+            inner.quick_drop();
+        }
+    }
+}
+
+/// A wrapper which drops its content in the background reaper thread when it
+/// is dropped.
+///
+/// This allows to never forget to call `quick_drop`, even on early returns
+/// or when a `?` fails. The content can be accessed thanks to `Deref` and
+/// `DerefMut`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use shpat::prelude::*;
+///
+/// struct Cache {
+///     entries: QuickDropBox<HashMap<u32, String>>,
+/// }
+///
+/// let mut cache = Cache {
+///     entries: QuickDropBox::new(HashMap::new()),
+/// };
+/// cache.entries.insert(42, String::from("foo"));
+///
+/// assert_eq!(cache.entries.get(&42).unwrap(), "foo");
+///
+/// // The hash map is dropped in the background.
+/// drop(cache);
+/// ```
+pub struct QuickDropBox<T: Send + 'static> {
+    /// The wrapped value. It is `None` only once it has been moved out, in
+    /// `into_inner` or `drop`.
+    inner: Option<T>,
+}
+
+impl<T: Send + 'static> QuickDropBox<T> {
+    /// Wraps `value`, so that it is dropped in the background.
+    pub fn new(value: T) -> QuickDropBox<T> {
+        QuickDropBox { inner: Some(value) }
+    }
+
+    /// Returns the wrapped value. It will not be dropped in the background
+    /// anymore.
+    pub fn into_inner(mut self) -> T {
+        self.inner.take().expect("QuickDropBox is always filled")
+    }
+}
+
+impl<T: Send + 'static> Deref for QuickDropBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.inner.as_ref().expect("QuickDropBox is always filled")
+    }
+}
+
+impl<T: Send + 'static> DerefMut for QuickDropBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.inner.as_mut().expect("QuickDropBox is always filled")
+    }
+}
+
+impl<T: Send + 'static> Drop for QuickDropBox<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            inner.quick_drop();
+        }
+    }
+}
+
+impl<T: Send + 'static> From<T> for QuickDropBox<T> {
+    fn from(value: T) -> QuickDropBox<T> {
+        QuickDropBox::new(value)
+    }
+}
+
+impl<T: Send + Default + 'static> Default for QuickDropBox<T> {
+    fn default() -> QuickDropBox<T> {
+        QuickDropBox::new(T::default())
+    }
+}
+
+impl<T: Send + Debug + 'static> Debug for QuickDropBox<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("QuickDropBox").field(&**self).finish()
+    }
+}
+
+/// Serializes the tests which change or depend on the global configuration.
+#[cfg(test)]
+static GLOBAL_CONFIG: Mutex<()> = Mutex::new(());
+
+/// Locks `GLOBAL_CONFIG`, ignoring poisoning caused by a failed test.
+#[cfg(test)]
+fn lock_global_config() -> MutexGuard<'static, ()> {
+    GLOBAL_CONFIG.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Objects recording how they are dropped, shared by the tests.
+#[cfg(test)]
+mod fixtures {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            mpsc::{Receiver, Sender},
+            Arc,
+        },
+        thread::{self, ThreadId},
+    };
+
+    /// Sends the id of the thread in which it is dropped.
+    pub(super) struct Witness(pub(super) Sender<ThreadId>);
+
+    impl Drop for Witness {
+        fn drop(&mut self) {
+            let _ = self.0.send(thread::current().id());
+        }
+    }
+
+    /// Sets a flag when dropped.
+    pub(super) struct Flagged(pub(super) Arc<AtomicBool>);
+
+    impl Drop for Flagged {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Increments a counter when dropped.
+    pub(super) struct Counted<'a>(pub(super) &'a AtomicUsize);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Blocks the thread dropping it until a message is received, or until
+    /// the sender is dropped.
+    pub(super) struct Gate(pub(super) Receiver<()>);
+
+    impl Drop for Gate {
+        fn drop(&mut self) {
+            let _ = self.0.recv();
+        }
+    }
+
+    /// Panics when dropped.
+    pub(super) struct Bomb;
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            panic!("boom");
+        }
+    }
+}
+
+#[cfg(test)]
+mod simple_object {
+    use super::*;
+
+    #[allow(dead_code)]
+    struct S {
+        a: u8,
+        b: u32,
+        c: char,
+    }
+
+    #[test]
+    fn quick_drop() {
+        let s = S {
+            a: 42,
+            b: 101,
+            c: '_',
+        };
+
+        s.quick_drop();
+    }
+}
+
+#[cfg(test)]
+mod with_generic_type {
+    use super::*;
+
+    #[allow(dead_code)]
+    struct S<T> {
+        val: T,
+    }
+
+    #[test]
+    fn quick_drop() {
+        let s = S {
+            val: String::from("foo"),
+        };
+
+        s.quick_drop();
+    }
+}
+
+#[cfg(test)]
+mod with_static_lifetime {
+    use super::*;
+
+    #[allow(dead_code)]
+    struct S<'a> {
+        val: &'a usize,
+    }
+
+    #[test]
+    fn quick_drop() {
+        let s: S<'static> = S {
+            val: &42,
+        };
+
+        s.quick_drop();
+    }
+}
+
+#[cfg(test)]
+mod drop_side_effects {
+    use super::*;
+
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::fixtures::Witness;
+
+    #[test]
+    fn dropped_in_the_same_background_thread() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+
+        Witness(sender.clone()).quick_drop();
+        Witness(sender).quick_drop();
+
+        let first = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let second = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_ne!(first, thread::current().id());
+        assert_eq!(first, second);
+    }
+}
+
+#[cfg(test)]
+mod inline_threshold {
+    use super::*;
+
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::fixtures::Witness;
+
+    /// A `Witness` reporting an arbitrary drop cost.
+    #[allow(dead_code)]
+    struct Costly(Witness, usize);
+
+    impl DropCost for Costly {
+        fn drop_cost(&self) -> usize {
+            self.1
+        }
+    }
+
+    #[test]
+    fn no_drop_glue_is_inline() {
+        assert!(42u64.quick_drop_handle().is_dropped());
+        assert!([0u8; 1 << 16].quick_drop_handle().is_dropped());
+    }
+
+    #[test]
+    fn cheap_is_inline() {
+        let (sender, receiver) = mpsc::channel();
+
+        Costly(Witness(sender), 0).quick_drop_measured();
+
+        assert_eq!(receiver.try_recv(), Ok(thread::current().id()));
+    }
+
+    #[test]
+    fn expensive_is_in_background() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+
+        Costly(Witness(sender), usize::MAX / 2).quick_drop_measured();
+        let dropper = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_ne!(dropper, thread::current().id());
+    }
+
+    #[test]
+    fn maximal_cost_does_not_overflow() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+
+        let boxed = Box::new(Costly(Witness(mpsc::channel().0), usize::MAX));
+        assert_eq!(boxed.drop_cost(), usize::MAX);
+
+        Costly(Witness(sender), usize::MAX).quick_drop_measured();
+        let dropper = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_ne!(dropper, thread::current().id());
+    }
+
+    #[test]
+    fn collection_costs() {
+        assert_eq!(Vec::<u64>::with_capacity(10).drop_cost(), 80);
+        assert_eq!(String::with_capacity(10).drop_cost(), 10);
+        assert_eq!(
+            Box::new(Vec::<u8>::new()).drop_cost(),
+            mem::size_of::<Vec<u8>>()
+        );
+    }
+
+    #[test]
+    fn nested_heap_memory() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+        let nested = vec![vec![0u8; 1 << 20]; 4];
+
+        assert_eq!(nested.drop_cost(), usize::MAX);
+        assert_eq!(
+            Vec::<Vec<u8>>::with_capacity(4).drop_cost(),
+            4 * mem::size_of::<Vec<u8>>()
+        );
+
+        nested.quick_drop_measured();
+        vec![Witness(sender)].quick_drop_measured();
+        let dropper = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_ne!(dropper, thread::current().id());
+    }
+}
+
+#[cfg(test)]
+mod quick_drop_box {
+    use super::*;
+
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use super::fixtures::Flagged;
+
+    #[test]
+    fn deref() {
+        let mut b = QuickDropBox::new(vec![1, 2]);
+        b.push(3);
+
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.into_inner(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn dropped_in_background() {
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let _lock = lock_global_config();
+        drop(QuickDropBox::new(Flagged(Arc::clone(&flag))));
+        flush();
+
+        assert!(flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn into_inner_is_not_dropped() {
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let _lock = lock_global_config();
+        let inner = QuickDropBox::new(Flagged(Arc::clone(&flag))).into_inner();
+        flush();
+
+        assert!(!flag.load(Ordering::SeqCst));
+        drop(inner);
+    }
+}
+
+#[cfg(test)]
+mod waiting {
+    use super::*;
+
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    };
+
+    use super::fixtures::{Counted, Gate};
+
+    #[test]
+    fn handle_wait() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let handle = Counted(&COUNTER).quick_drop_handle();
+        handle.wait();
+
+        assert_eq!(COUNTER.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn handle_is_dropped() {
+        let _lock = lock_global_config();
+        let (open, gate) = mpsc::channel::<()>();
+
+        let handle = Gate(gate).quick_drop_handle();
+        let dropped_early = handle.is_dropped();
+        drop(open);
+
+        let start = Instant::now();
+        while !handle.is_dropped() && start.elapsed() < Duration::from_secs(5) {
+            thread::yield_now();
+        }
+
+        assert!(!dropped_early);
+        assert!(handle.is_dropped());
+    }
+
+    #[test]
+    fn flush_waits_for_previous_drops() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let _lock = lock_global_config();
+
+        for _ in 0..100 {
+            Counted(&COUNTER).quick_drop();
+        }
+        flush();
+
+        assert_eq!(COUNTER.load(Ordering::SeqCst), 100);
+    }
+}
+
+#[cfg(test)]
+mod panicking_destructor {
+    use super::*;
+
+    use std::{sync::mpsc, time::Duration};
+
+    use super::fixtures::Bomb;
+
+    #[test]
+    fn panic_is_reported_to_hook() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+
+        set_panic_hook(move |panic| {
+            let _ = sender.lock().unwrap().send(panic.clone());
+        });
+        let count = panic_count();
+        Bomb.quick_drop_handle().wait();
+        drop(take_panic_hook());
+
+        let panic = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(panic.type_name().ends_with("Bomb"));
+        assert_eq!(panic.message(), "boom");
+        assert!(panic_count() > count);
+
+        // The reaper survived.
+        vec![1].quick_drop_handle().wait();
+    }
+
+    #[test]
+    fn panicking_hook() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+
+        set_panic_hook(|_| panic!("hook"));
+        Bomb.quick_drop();
+        thread::spawn(move || {
+            flush();
+            let _ = sender.send(());
+        });
+        let flushed = receiver.recv_timeout(Duration::from_secs(5));
+        drop(take_panic_hook());
+
+        assert!(flushed.is_ok());
+
+        // The reaper survived.
+        vec![1].quick_drop_handle().wait();
+    }
+
+    #[test]
+    fn repanic_on_flush() {
+        let _lock = lock_global_config();
+
+        set_repanic_on_flush(true);
+        Bomb.quick_drop();
+        let first = panic::catch_unwind(flush);
+        let second = panic::catch_unwind(flush);
+        set_repanic_on_flush(false);
+
+        assert!(first.is_err());
+        assert!(second.is_ok());
+    }
+}
+
+#[cfg(test)]
+mod scoped {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::fixtures::Counted;
+
+    #[test]
+    fn borrowed_objects_are_dropped_before_return() {
+        let counter = AtomicUsize::new(0);
+
+        scope(|s| {
+            for _ in 0..100 {
+                s.quick_drop(Counted(&counter));
+            }
+        });
+
+        assert_eq!(counter.load(Ordering::SeqCst), 100);
+    }
+
+    #[test]
+    fn borrowed_str() {
+        let text = String::from("foo bar baz");
+        let words: Vec<&str> = text.split(' ').collect();
+
+        let first = scope(|s| {
+            let first = words[0];
+            s.quick_drop(words);
+            first
+        });
+
+        assert_eq!(first, "foo");
+    }
+}
+
+#[cfg(test)]
+mod parallel {
+    use super::*;
+
+    use std::{
+        collections::HashSet,
+        sync::{mpsc, Mutex},
+        time::Duration,
+    };
+
+    use super::fixtures::{Bomb, Witness};
+
+    #[test]
+    fn dropped_in_several_threads() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+        let v: Vec<_> = (0..10 * MIN_CHUNK_LEN)
+            .map(|_| Witness(sender.clone()))
+            .collect();
+
+        set_parallelism(4);
+        v.quick_drop_parallel();
+        flush();
+        set_parallelism(0);
+
+        let dropped_in: Vec<_> = receiver.try_iter().collect();
+        let threads: HashSet<_> = dropped_in.iter().collect();
+        assert_eq!(dropped_in.len(), 10 * MIN_CHUNK_LEN);
+        // A worker may drop several chunks.
+        assert!(threads.len() >= 2);
+        assert!(!threads.contains(&thread::current().id()));
+    }
+
+    /// Returns the number of drop workers started so far.
+    fn worker_count() -> usize {
+        WORKERS.lock().unwrap().as_ref().map_or(0, |w| w.count)
+    }
+
+    #[test]
+    fn workers_are_reused() {
+        let _lock = lock_global_config();
+        let v: Vec<_> = (0..10 * MIN_CHUNK_LEN).map(|n| n.to_string()).collect();
+
+        set_parallelism(4);
+        v.clone().quick_drop_parallel();
+        flush();
+        let started = worker_count();
+        v.quick_drop_parallel();
+        flush();
+        let restarted = worker_count();
+        set_parallelism(0);
+
+        assert!(started >= 3);
+        assert_eq!(started, restarted);
+    }
+
+    #[test]
+    fn no_drop_glue_is_not_split() {
+        let _lock = lock_global_config();
+        let v = vec![0u64; 10 * MIN_CHUNK_LEN];
+
+        set_parallelism(4);
+        let sent = CHUNKS_SENT.load(Ordering::Relaxed);
+        v.quick_drop_parallel();
+        flush();
+        set_parallelism(0);
+
+        assert_eq!(CHUNKS_SENT.load(Ordering::Relaxed), sent);
+    }
+
+    #[test]
+    fn worker_panic_is_reported() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+
+        set_panic_hook(move |panic| {
+            let _ = sender.lock().unwrap().send(panic.clone());
+        });
+        set_parallelism(2);
+        let mut v: Vec<Option<Bomb>> = (0..2 * MIN_CHUNK_LEN).map(|_| None).collect();
+        v[2 * MIN_CHUNK_LEN - 1] = Some(Bomb);
+        v.quick_drop_parallel();
+        flush();
+        set_parallelism(0);
+        drop(take_panic_hook());
+
+        let panic = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(panic.message(), "boom");
+    }
+
+    #[test]
+    fn small_collections_are_not_split() {
+        let (sender, receiver) = mpsc::channel();
+        let v: VecDeque<_> = (0..10).map(|_| Witness(sender.clone())).collect();
+
+        Chunked::new(v).quick_drop_handle().wait();
+
+        let threads: HashSet<_> = receiver.try_iter().collect();
+        assert_eq!(threads.len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod statistics {
+    use super::*;
+
+    /// Takes some time to drop.
+    struct Slow;
+
+    impl Drop for Slow {
+        fn drop(&mut self) {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn completed_and_time() {
+        let before = stats();
+        Slow.quick_drop_handle().wait();
+        let after = stats();
+
+        assert!(after.completed > before.completed);
+        assert!(after.time_in_drop >= before.time_in_drop + Duration::from_millis(10));
+    }
+
+    #[test]
+    fn per_type() {
+        let _lock = lock_global_config();
+
+        /// A type only dropped by this test.
+        #[allow(dead_code)]
+        struct Tracked(Slow);
+
+        set_per_type_stats(true);
+        Tracked(Slow).quick_drop_handle().wait();
+        Tracked(Slow).quick_drop_handle().wait();
+        vec![String::new()].quick_drop_handle().wait();
+        let stats = stats();
+        set_per_type_stats(false);
+
+        let slow = stats
+            .per_type
+            .iter()
+            .find(|t| t.type_name == any::type_name::<Tracked>())
+            .unwrap();
+        assert_eq!(slow.count, 2);
+        assert!(slow.max_time >= Duration::from_millis(10));
+        assert!(slow.total_time >= Duration::from_millis(20));
+        assert!(stats
+            .per_type
+            .iter()
+            .any(|t| t.type_name == any::type_name::<Vec<String>>()));
+    }
+}
+
+#[cfg(test)]
+mod backpressure {
+    use super::*;
+
+    use std::{sync::mpsc, time::Duration};
+
+    use super::fixtures::{Gate, Witness};
+
+    #[test]
+    fn inline_when_full() {
+        let _lock = lock_global_config();
+        let (open, gate) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+
+        set_capacity(Some(1));
+        set_backpressure(Backpressure::Inline);
+        Gate(gate).quick_drop();
+        Witness(sender).quick_drop();
+        let dropper = receiver.try_recv();
+        open.send(()).unwrap();
+        set_capacity(None);
+        set_backpressure(Backpressure::Block);
+        flush();
+
+        assert_eq!(dropper, Ok(thread::current().id()));
+    }
+
+    #[test]
+    fn block_when_full() {
+        let _lock = lock_global_config();
+        let (open, gate) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+
+        set_capacity(Some(1));
+        Gate(gate).quick_drop();
+        let producer = thread::spawn(move || Witness(sender).quick_drop());
+        thread::sleep(Duration::from_millis(50));
+        let blocked = !producer.is_finished();
+        open.send(()).unwrap();
+        producer.join().unwrap();
+        set_capacity(None);
+        flush();
+
+        assert!(blocked);
+        assert_ne!(receiver.try_recv().unwrap(), thread::current().id());
+    }
+
+    #[test]
+    fn grow_when_full() {
+        let _lock = lock_global_config();
+        let (open, gate) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+
+        set_capacity(Some(1));
+        set_backpressure(Backpressure::Grow);
+        Gate(gate).quick_drop();
+        Witness(sender).quick_drop();
+        let pending = stats().pending;
+        open.send(()).unwrap();
+        set_capacity(None);
+        set_backpressure(Backpressure::Block);
+        flush();
+
+        assert!(pending >= 2);
+        assert_ne!(receiver.try_recv().unwrap(), thread::current().id());
+    }
+
+    #[test]
+    fn block_with_zero_capacity() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+
+        set_capacity(Some(0));
+        Witness(sender).quick_drop();
+        set_capacity(None);
+
+        assert_eq!(receiver.try_recv(), Ok(thread::current().id()));
+    }
+}
+
+#[cfg(test)]
+mod shared {
+    use super::*;
+
+    use std::{sync::mpsc, time::Duration};
+
+    use super::fixtures::Witness;
+
+    #[test]
+    fn arc() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+        let shared = Arc::new(Witness(sender));
+        let other = Arc::clone(&shared);
+
+        shared.quick_drop_last();
+        assert_eq!(Arc::strong_count(&other), 1);
+
+        other.quick_drop_last();
+        let dropper = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_ne!(dropper, thread::current().id());
+    }
+
+    #[test]
+    fn unsized_arc() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+        let shared: Arc<[Witness]> = Arc::new([Witness(sender)]);
+        let text: Arc<str> = Arc::from("hello");
+        let any: Arc<dyn Any + Send + Sync> = Arc::new(42);
+
+        shared.quick_drop_last();
+        text.quick_drop_last();
+        any.quick_drop_last();
+
+        let dropper = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_ne!(dropper, thread::current().id());
+    }
+
+    #[test]
+    fn rc() {
+        let _lock = lock_global_config();
+        let (sender, receiver) = mpsc::channel();
+        let shared = Rc::new(Witness(sender));
+        let other = Rc::clone(&shared);
+
+        shared.quick_drop_last();
+        assert_eq!(Rc::strong_count(&other), 1);
+
+        other.quick_drop_last();
+        let dropper = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_ne!(dropper, thread::current().id());
+    }
+}
+
+#[cfg(test)]
+mod runtime {
+    use super::*;
+
+    use std::sync::{mpsc, Arc};
+
+    use super::fixtures::Gate;
+
+    /// Sets a flag when dropped, after some time.
+    struct Slow(Arc<AtomicBool>);
+
+    impl Drop for Slow {
+        fn drop(&mut self) {
+            thread::sleep(Duration::from_millis(50));
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn waits_for_pending_drops() {
+        let flag = Arc::new(AtomicBool::new(false));
+
+        {
+            let _runtime = QuickDropRuntime::new();
+            Slow(Arc::clone(&flag)).quick_drop();
+        }
+
+        assert!(flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn timeout() {
+        let _lock = lock_global_config();
+        let (open, gate) = mpsc::channel();
+
+        {
+            let _runtime = QuickDropRuntime::with_timeout(Duration::from_millis(50));
+            Gate(gate).quick_drop();
+        }
+
+        assert!(!wait_idle_timeout(Duration::ZERO));
+        open.send(()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod inline_mode {
+    use super::*;
+
+    use std::sync::mpsc;
+
+    use super::fixtures::Witness;
+
+    #[test]
+    fn guard() {
+        let (sender, receiver) = mpsc::channel();
+
+        {
+            let _inline = inline_drops();
+            Witness(sender.clone()).quick_drop();
+            drop(QuickDropBox::new(Witness(sender)));
+
+            let dropped_in: Vec<_> = receiver.try_iter().collect();
+            assert_eq!(dropped_in, [thread::current().id(); 2]);
+        }
+
+        assert!(!inline_drops_enabled());
+    }
+
+    #[test]
+    fn nested_guards() {
+        let outer = inline_drops();
+        let inner = inline_drops();
+        drop(inner);
+        assert!(inline_drops_enabled());
+
+        drop(outer);
+        assert!(!inline_drops_enabled());
+    }
+
+    #[test]
+    fn only_current_thread() {
+        let _inline = inline_drops();
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || sender.send(inline_drops_enabled()).unwrap());
+
+        assert!(!receiver.recv().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod executor {
+    use super::*;
+
+    use std::sync::atomic::AtomicBool;
+
+    use super::fixtures::Flagged;
+
+    /// Keeps the jobs it receives, so that they can be run later.
+    #[derive(Default)]
+    struct Deferred(Mutex<Vec<DropJob>>);
+
+    impl DropExecutor for Deferred {
+        fn execute(&self, job: DropJob) {
+            self.0.lock().unwrap().push(job);
+        }
+    }
+
+    #[test]
+    fn per_call() {
+        let executor = Deferred::default();
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let handle = Flagged(Arc::clone(&flag)).quick_drop_on_handle(&executor);
+        assert!(!handle.is_dropped());
+        assert!(!flag.load(Ordering::SeqCst));
+
+        let jobs = mem::take(&mut *executor.0.lock().unwrap());
+        assert_eq!(jobs.len(), 1);
+        assert!(jobs[0].type_name().ends_with("Flagged"));
+        jobs.into_iter().for_each(DropJob::run);
+
+        assert!(handle.is_dropped());
+        assert!(flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn dropped_job_is_run() {
+        let flag = Arc::new(AtomicBool::new(false));
+
+        Flagged(Arc::clone(&flag)).quick_drop_on(&drop);
+
+        assert!(flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn global() {
+        let _lock = lock_global_config();
+        let executed = Arc::new(AtomicUsize::new(0));
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let counter = Arc::clone(&executed);
+        set_executor(move |job: DropJob| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            job.run();
+        });
+        Flagged(Arc::clone(&flag)).quick_drop();
+        reset_executor();
+
+        // Objects dropped by other tests may have been handed to the executor
+        // as well.
+        assert!(executed.load(Ordering::SeqCst) >= 1);
+        assert!(flag.load(Ordering::SeqCst));
     }
 }
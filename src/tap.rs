@@ -0,0 +1,86 @@
+//! Inspecting a value in the middle of a method chain.
+//!
+//! It is sometimes needed to look at a value without modifying it, for
+//! instance to log it or to check an invariant. The `Tap` trait allows to do
+//! it without breaking the method chain:
+//!
+//! ```rust
+//! use shpat::prelude::*;
+//!
+//! let v = vec![3, 1, 2]
+//!     .tap(|v| println!("before sorting: {:?}", v))
+//!     .apply(|v| v.sort())
+//!     .tap_dbg(|v| assert!(v.windows(2).all(|w| w[0] <= w[1])));
+//!
+//! assert_eq!(v, [1, 2, 3]);
+//! ```
+
+/// Allows to inspect a value in a method chain.
+pub trait Tap: Sized {
+    /// Calls `f` with a reference to `self`, then returns `self`.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let mut seen = 0;
+    /// let v = vec![1, 2, 3].tap(|v| seen = v.len());
+    ///
+    /// assert_eq!(seen, 3);
+    /// assert_eq!(v, [1, 2, 3]);
+    /// ```
+    fn tap<A, F: FnOnce(&Self) -> A>(self, f: F) -> Self {
+        f(&self);
+        self
+    }
+
+    /// Calls `f` with a reference to `self` if debug assertions are enabled,
+    /// then returns `self`.
+    ///
+    /// In release builds, `f` is not called at all. This makes it suitable
+    /// for expensive checks or debug logging.
+    ///
+    /// ```
+    /// use shpat::prelude::*;
+    ///
+    /// let v = vec![1, 2, 3].tap_dbg(|v| assert_eq!(v.len(), 3));
+    /// ```
+    fn tap_dbg<A, F: FnOnce(&Self) -> A>(self, f: F) -> Self {
+        if cfg!(debug_assertions) {
+            f(&self);
+        }
+        self
+    }
+}
+
+// Automatic implementation of the `Tap` trait for any sized type.
+impl<T: Sized> Tap for T {}
+
+#[cfg(test)]
+mod inspection {
+    use super::*;
+
+    use crate::apply::Apply;
+
+    #[test]
+    fn tap_in_chain() {
+        let mut lengths = Vec::new();
+
+        let v = Vec::new()
+            .apply(|v| v.push(42))
+            .tap(|v| lengths.push(v.len()))
+            .apply(|v| v.push(101))
+            .tap(|v| lengths.push(v.len()));
+
+        assert_eq!(v, [42, 101]);
+        assert_eq!(lengths, [1, 2]);
+    }
+
+    #[test]
+    fn tap_dbg_follows_debug_assertions() {
+        let mut called = false;
+        let v = 42.tap_dbg(|_| called = true);
+
+        assert_eq!(v, 42);
+        assert_eq!(called, cfg!(debug_assertions));
+    }
+}
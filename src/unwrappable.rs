@@ -5,29 +5,457 @@
 //! underlying success value, or panic the program.
 //!
 //! This trait is closely tied to the `Try` trait.
+//!
+//! The `Fallible` trait goes further, and gives access to the failure value,
+//! which allows to write combinators which preserve the kind of container
+//! they are called on.
 
-use std::fmt::Debug;
+use std::{
+    any,
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    marker::PhantomData,
+    ops::ControlFlow,
+    task::Poll,
+};
 
 /// Unifies the behaviour of types which represent a success or a failure.
-pub trait Unwrappable<T>: Sized {
+///
+/// It is implemented for the following types:
+///   - `Option<T>`, where `None` is a failure,
+///   - `Result<T, E>`, where `Err` is a failure. This includes the
+///     `LockResult` and `TryLockResult` returned by `Mutex` and `RwLock`, and
+///     the `thread::Result` returned by `JoinHandle::join`. In the latter
+///     case, the panic message does not contain the message of the panic
+///     which stopped the thread,
+///   - `Poll<T>`, where `Pending` is a failure. A `Poll<Result<T, E>>` is
+///     unwrapped into a `Result<T, E>`, both layers can be unwrapped at once
+///     with `UnwrapAll`,
+///   - `ControlFlow<B, C>`, where `Break` is a failure, as it is the variant
+///     which exits early when used with the `?` operator.
+///
+/// Its methods are associated functions rather than methods, so that they do
+/// not shadow the inherent methods of `Option` and `Result`.
+///
+/// `Fallible` is a supertrait, which gives access to the same kind of
+/// container with another success type, as used by `rewrap`.
+///
+/// # Example
+///
+/// ```rust
+/// use shpat::prelude::*;
+///
+/// fn or_zero<U: Unwrappable<u32>>(u: U) -> u32 {
+///     Unwrappable::unwrap_or(u, 0)
+/// }
+///
+/// assert_eq!(or_zero(Some(42)), 42);
+/// assert_eq!(or_zero(Err::<u32, _>("nope")), 0);
+/// ```
+pub trait Unwrappable<T>: Fallible<Success = T> {
     /// Returns the underlying value, or panics the program if `self` is a
     /// failure.
+    #[track_caller]
     fn unwrap(s: Self) -> T;
+
+    /// Returns the underlying value, or panics the program with a message
+    /// containing `msg` if `self` is a failure.
+    #[track_caller]
+    fn expect(s: Self, msg: &str) -> T;
+
+    /// Returns `true` if `s` represents a success.
+    fn is_success(s: &Self) -> bool;
+
+    /// Returns the underlying value, or `default` if `s` is a failure.
+    fn unwrap_or(s: Self, default: T) -> T {
+        Self::unwrap_or_else(s, || default)
+    }
+
+    /// Returns the underlying value, or the value returned by `f` if `s` is a
+    /// failure.
+    ///
+    /// Unlike `Result::unwrap_or_else`, `f` does not receive the error, as
+    /// not every failure carries a value.
+    fn unwrap_or_else<F: FnOnce() -> T>(s: Self, f: F) -> T {
+        if Self::is_success(&s) {
+            Self::unwrap(s)
+        } else {
+            f()
+        }
+    }
+
+    /// Returns the underlying value, or the default value of `T` if `s` is a
+    /// failure.
+    fn unwrap_or_default(s: Self) -> T
+    where
+        T: Default,
+    {
+        Self::unwrap_or_else(s, T::default)
+    }
+
+    /// Replaces the success value of `s` by `value`, keeping the failure
+    /// untouched.
+    fn rewrap<S>(s: Self, value: S) -> Self::Map<S> {
+        Fallible::map_success(s, |_| value)
+    }
 }
 
 impl<T, E> Unwrappable<T> for Result<T, E>
 where
     E: Debug,
 {
+    #[track_caller]
     fn unwrap(s: Self) -> T {
         Result::unwrap(s)
     }
+
+    #[track_caller]
+    fn expect(s: Self, msg: &str) -> T {
+        Result::expect(s, msg)
+    }
+
+    fn is_success(s: &Self) -> bool {
+        s.is_ok()
+    }
 }
 
 impl<T> Unwrappable<T> for Option<T> {
+    #[track_caller]
     fn unwrap(s: Self) -> T {
         Option::unwrap(s)
     }
+
+    #[track_caller]
+    fn expect(s: Self, msg: &str) -> T {
+        Option::expect(s, msg)
+    }
+
+    fn is_success(s: &Self) -> bool {
+        s.is_some()
+    }
+}
+
+impl<T> Unwrappable<T> for Poll<T> {
+    #[track_caller]
+    fn unwrap(s: Self) -> T {
+        match s {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("called `Unwrappable::unwrap()` on a `Poll::Pending` value"),
+        }
+    }
+
+    #[track_caller]
+    fn expect(s: Self, msg: &str) -> T {
+        match s {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("{}", msg),
+        }
+    }
+
+    fn is_success(s: &Self) -> bool {
+        s.is_ready()
+    }
+}
+
+impl<B, C> Unwrappable<C> for ControlFlow<B, C>
+where
+    B: Debug,
+{
+    #[track_caller]
+    fn unwrap(s: Self) -> C {
+        match s {
+            ControlFlow::Continue(value) => value,
+            ControlFlow::Break(b) => panic!(
+                "called `Unwrappable::unwrap()` on a `ControlFlow::Break` value: {:?}",
+                b
+            ),
+        }
+    }
+
+    #[track_caller]
+    fn expect(s: Self, msg: &str) -> C {
+        match s {
+            ControlFlow::Continue(value) => value,
+            ControlFlow::Break(b) => panic!("{}: {:?}", msg, b),
+        }
+    }
+
+    fn is_success(s: &Self) -> bool {
+        s.is_continue()
+    }
+}
+
+/// Marks the innermost value in the `Depth` parameter of `UnwrapAll`.
+#[derive(Debug)]
+pub struct Innermost;
+
+/// Marks a layer of `Unwrappable` in the `Depth` parameter of `UnwrapAll`.
+///
+/// `U` is the type of the value wrapped by this layer, and `D` the depth of
+/// the following layers.
+#[derive(Debug)]
+pub struct Layer<U, D>(PhantomData<(U, D)>);
+
+/// Unwraps nested `Unwrappable`s, such as `Result<Option<T>, E>`, down to the
+/// innermost value.
+///
+/// `T` is the type of the innermost value, and `Depth` is a type-level list
+/// of the layers to unwrap, which is inferred by the compiler. As any type
+/// is its own innermost value, `T` has to be known for `Depth` to be
+/// inferred. It is usually given by a type annotation.
+///
+/// It is implemented for every nested `Unwrappable`, and can not be
+/// implemented outside of this crate.
+///
+/// # Panics
+///
+/// `unwrap_all` panics if any layer is a failure. The panic message says
+/// which layer failed, the outermost one being the layer 1.
+///
+/// # Example
+///
+/// ```rust
+/// use shpat::prelude::*;
+///
+/// let nested: Result<Option<u32>, String> = Ok(Some(42));
+/// let value: u32 = UnwrapAll::unwrap_all(nested);
+///
+/// assert_eq!(value, 42);
+/// ```
+///
+/// ```should_panic
+/// use shpat::prelude::*;
+///
+/// let nested: Option<Result<u32, String>> = Some(Err(String::from("oops")));
+///
+/// // Panics, saying that the layer 2, the `Result`, is a failure.
+/// let value: u32 = UnwrapAll::unwrap_all(nested);
+/// ```
+///
+/// Generic code has to name the `Depth` parameter, using `Layer` and
+/// `Innermost`:
+///
+/// ```rust
+/// use shpat::prelude::*;
+///
+/// fn port<U: UnwrapAll<u16, Layer<Result<u16, String>, Layer<u16, Innermost>>>>(u: U) -> u16 {
+///     UnwrapAll::unwrap_all(u)
+/// }
+///
+/// assert_eq!(port(Some(Ok::<_, String>(8080))), 8080);
+/// ```
+pub trait UnwrapAll<T, Depth>: layers::UnwrapLayers<T, Depth> {
+    /// Returns the innermost value, or panics the program if any layer is a
+    /// failure.
+    #[track_caller]
+    fn unwrap_all(s: Self) -> T {
+        layers::UnwrapLayers::unwrap_from_layer(s, 1)
+    }
+}
+
+impl<T, D, U: layers::UnwrapLayers<T, D>> UnwrapAll<T, D> for U {}
+
+/// The layer-by-layer unwrapping behind `UnwrapAll`, kept out of its public
+/// interface.
+mod layers {
+    use super::*;
+
+    /// Unwraps the layers of a nested `Unwrappable`, keeping track of the
+    /// current layer for the panic message.
+    pub trait UnwrapLayers<T, Depth>: Sized {
+        /// Same as `unwrap_all`, knowing that `s` is the layer number
+        /// `layer`.
+        #[track_caller]
+        fn unwrap_from_layer(s: Self, layer: usize) -> T;
+    }
+
+    impl<T> UnwrapLayers<T, Innermost> for T {
+        fn unwrap_from_layer(s: Self, _: usize) -> T {
+            s
+        }
+    }
+
+    impl<T, U, D, W> UnwrapLayers<T, Layer<U, D>> for W
+    where
+        W: Unwrappable<U>,
+        U: UnwrapLayers<T, D>,
+    {
+        #[track_caller]
+        fn unwrap_from_layer(s: Self, layer: usize) -> T {
+            let inner = if Unwrappable::is_success(&s) {
+                Unwrappable::unwrap(s)
+            } else {
+                let msg = format!(
+                    "unwrap_all failed at layer {} (`{}`)",
+                    layer,
+                    any::type_name::<W>()
+                );
+                Unwrappable::expect(s, &msg)
+            };
+
+            UnwrapLayers::unwrap_from_layer(inner, layer + 1)
+        }
+    }
+}
+
+/// The failure value of an `Option`, when it is seen as a `Fallible`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NoneError;
+
+impl Display for NoneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("value is `None`")
+    }
+}
+
+impl Error for NoneError {}
+
+/// The failure value of a `Poll`, when it is seen as a `Fallible`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PendingError;
+
+impl Display for PendingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("value is `Pending`")
+    }
+}
+
+impl Error for PendingError {}
+
+/// A type which represents either a success, holding a `Success` value, or a
+/// failure, holding a `Failure` value.
+///
+/// This is the companion of `Unwrappable`, for code which needs to transform
+/// the success value while keeping the container shape: mapping an `Option`
+/// gives an `Option`, and mapping a `Result` gives a `Result` with the same
+/// error type.
+///
+/// Only `into_result` and `from_result` have to be implemented, the
+/// combinators are derived from them.
+///
+/// # Example
+///
+/// ```rust
+/// use shpat::prelude::*;
+///
+/// fn double<F: Fallible<Success = u32>>(f: F) -> F::Map<u32> {
+///     Fallible::map_success(f, |n| n * 2)
+/// }
+///
+/// assert_eq!(double(Some(21)), Some(42));
+/// assert_eq!(double(Err::<u32, _>("nope")), Err("nope"));
+/// ```
+pub trait Fallible: Sized {
+    /// The value held in case of success.
+    type Success;
+
+    /// The value held in case of failure.
+    type Failure;
+
+    /// The same kind of container, holding a success value of type `S`.
+    type Map<S>: Fallible<Success = S, Failure = Self::Failure>;
+
+    /// Converts `f` into a `Result`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the failure value of `f`, if it is a failure.
+    fn into_result(f: Self) -> Result<Self::Success, Self::Failure>;
+
+    /// Converts a `Result` back into this kind of container.
+    fn from_result(r: Result<Self::Success, Self::Failure>) -> Self;
+
+    /// Applies `op` to the success value of `f`, leaving a failure
+    /// untouched.
+    fn map_success<S, F: FnOnce(Self::Success) -> S>(f: Self, op: F) -> Self::Map<S> {
+        Fallible::from_result(Self::into_result(f).map(op))
+    }
+
+    /// Calls `op` with the success value of `f`, and returns its result, or
+    /// returns the failure of `f`.
+    fn and_then<S, F: FnOnce(Self::Success) -> Self::Map<S>>(f: Self, op: F) -> Self::Map<S> {
+        match Self::into_result(f) {
+            Ok(success) => op(success),
+            Err(failure) => Fallible::from_result(Err(failure)),
+        }
+    }
+
+    /// Calls `op` with the failure value of `f`, and returns its result, or
+    /// returns `f` if it is a success.
+    fn or_else<F: FnOnce(Self::Failure) -> Self>(f: Self, op: F) -> Self {
+        match Self::into_result(f) {
+            Ok(success) => Self::from_result(Ok(success)),
+            Err(failure) => op(failure),
+        }
+    }
+}
+
+impl<T, E> Fallible for Result<T, E> {
+    type Success = T;
+    type Failure = E;
+    type Map<S> = Result<S, E>;
+
+    fn into_result(f: Self) -> Result<T, E> {
+        f
+    }
+
+    fn from_result(r: Result<T, E>) -> Self {
+        r
+    }
+}
+
+impl<T> Fallible for Option<T> {
+    type Success = T;
+    type Failure = NoneError;
+    type Map<S> = Option<S>;
+
+    fn into_result(f: Self) -> Result<T, NoneError> {
+        f.ok_or(NoneError)
+    }
+
+    fn from_result(r: Result<T, NoneError>) -> Self {
+        r.ok()
+    }
+}
+
+impl<T> Fallible for Poll<T> {
+    type Success = T;
+    type Failure = PendingError;
+    type Map<S> = Poll<S>;
+
+    fn into_result(f: Self) -> Result<T, PendingError> {
+        match f {
+            Poll::Ready(value) => Ok(value),
+            Poll::Pending => Err(PendingError),
+        }
+    }
+
+    fn from_result(r: Result<T, PendingError>) -> Self {
+        match r {
+            Ok(value) => Poll::Ready(value),
+            Err(PendingError) => Poll::Pending,
+        }
+    }
+}
+
+impl<B, C> Fallible for ControlFlow<B, C> {
+    type Success = C;
+    type Failure = B;
+    type Map<S> = ControlFlow<B, S>;
+
+    fn into_result(f: Self) -> Result<C, B> {
+        match f {
+            ControlFlow::Continue(value) => Ok(value),
+            ControlFlow::Break(b) => Err(b),
+        }
+    }
+
+    fn from_result(r: Result<C, B>) -> Self {
+        match r {
+            Ok(value) => ControlFlow::Continue(value),
+            Err(b) => ControlFlow::Break(b),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -49,6 +477,57 @@ mod result {
         let r: Result<(), _> = Err(42);
         Unwrappable::unwrap(r);
     }
+
+    #[test]
+    #[should_panic(expected = "foo failed: 42")]
+    fn expect_err_path() {
+        let r: Result<(), _> = Err(42);
+        Unwrappable::expect(r, "foo failed");
+    }
+
+    #[test]
+    fn is_success() {
+        assert!(Unwrappable::is_success(&Ok::<_, ()>(42)));
+        assert!(!Unwrappable::is_success(&Err::<(), _>(42)));
+    }
+
+    #[test]
+    fn unwrap_or() {
+        assert_eq!(Unwrappable::unwrap_or(Ok::<_, ()>(42), 101), 42);
+        assert_eq!(Unwrappable::unwrap_or(Err(()), 101), 101);
+
+        assert_eq!(Unwrappable::unwrap_or_else(Ok::<_, ()>(42), || 101), 42);
+        assert_eq!(Unwrappable::unwrap_or_else(Err(()), || 101), 101);
+
+        assert_eq!(Unwrappable::unwrap_or_default(Ok::<_, ()>(42)), 42);
+        assert_eq!(Unwrappable::unwrap_or_default(Err::<u32, _>(())), 0);
+    }
+
+    #[test]
+    fn fallible_combinators() {
+        let ok: Result<u8, &str> = Ok(42);
+        let err: Result<u8, &str> = Err("nope");
+
+        assert_eq!(Fallible::map_success(ok, char::from), Ok('*'));
+        assert_eq!(Fallible::map_success(err, char::from), Err("nope"));
+
+        assert_eq!(Fallible::and_then(ok, |n| Ok(n + 1)), Ok(43));
+        assert_eq!(Fallible::and_then(ok, |_| Err::<u8, _>("no")), Err("no"));
+
+        assert_eq!(Fallible::or_else(err, |e| Ok(e.len() as u8)), Ok(4));
+        assert_eq!(Fallible::or_else(ok, |_| Ok(0)), Ok(42));
+
+        assert_eq!(Fallible::into_result(err), Err("nope"));
+    }
+
+    #[test]
+    fn rewrap() {
+        let r: Result<_, ()> = Ok(42);
+        assert_eq!(Unwrappable::rewrap(r, 'a'), Ok('a'));
+
+        let r: Result<(), _> = Err(42);
+        assert_eq!(Unwrappable::rewrap(r, 'a'), Err(42));
+    }
 }
 
 #[cfg(test)]
@@ -70,4 +549,278 @@ mod option {
         let o: Option<()> = None;
         Unwrappable::unwrap(o);
     }
+
+    #[test]
+    #[should_panic(expected = "foo failed")]
+    fn expect_none_path() {
+        let o: Option<()> = None;
+        Unwrappable::expect(o, "foo failed");
+    }
+
+    #[test]
+    fn is_success() {
+        assert!(Unwrappable::is_success(&Some(42)));
+        assert!(!Unwrappable::is_success(&None::<()>));
+    }
+
+    #[test]
+    fn unwrap_or() {
+        assert_eq!(Unwrappable::unwrap_or(Some(42), 101), 42);
+        assert_eq!(Unwrappable::unwrap_or(None, 101), 101);
+
+        assert_eq!(Unwrappable::unwrap_or_else(Some(42), || 101), 42);
+        assert_eq!(Unwrappable::unwrap_or_else(None, || 101), 101);
+
+        assert_eq!(Unwrappable::unwrap_or_default(Some(42)), 42);
+        assert_eq!(Unwrappable::unwrap_or_default(None::<u32>), 0);
+    }
+
+    #[test]
+    fn fallible_combinators() {
+        assert_eq!(Fallible::map_success(Some(42), |n| n + 1), Some(43));
+        assert_eq!(Fallible::map_success(None::<u8>, |n| n + 1), None);
+
+        assert_eq!(Fallible::and_then(Some(42), |_| None::<()>), None);
+        assert_eq!(Fallible::or_else(None, |NoneError| Some(101)), Some(101));
+
+        assert_eq!(Fallible::into_result(Some(42)), Ok(42));
+        assert_eq!(Fallible::into_result(None::<()>), Err(NoneError));
+    }
+
+    #[test]
+    fn rewrap() {
+        assert_eq!(Unwrappable::rewrap(Some(42), 'a'), Some('a'));
+        assert_eq!(Unwrappable::rewrap(None::<()>, 'a'), None);
+    }
+}
+
+#[cfg(test)]
+mod unwrap_all {
+    use super::*;
+
+    #[test]
+    fn nested_success() {
+        let r: Result<Option<u32>, ()> = Ok(Some(42));
+        let value: u32 = UnwrapAll::unwrap_all(r);
+        assert_eq!(value, 42);
+
+        let o: Option<Option<Option<char>>> = Some(Some(Some('a')));
+        let value: char = UnwrapAll::unwrap_all(o);
+        assert_eq!(value, 'a');
+    }
+
+    #[test]
+    #[should_panic(expected = "unwrap_all failed at layer 1")]
+    fn outer_failure() {
+        let r: Result<Option<u32>, &str> = Err("oops");
+        let _: u32 = UnwrapAll::unwrap_all(r);
+    }
+
+    #[test]
+    #[should_panic(expected = "unwrap_all failed at layer 2")]
+    fn inner_failure() {
+        let r: Result<Option<u32>, &str> = Ok(None);
+        let _: u32 = UnwrapAll::unwrap_all(r);
+    }
+
+    #[test]
+    #[should_panic(expected = "oops")]
+    fn failure_message() {
+        let o: Option<Result<u32, &str>> = Some(Err("oops"));
+        let _: u32 = UnwrapAll::unwrap_all(o);
+    }
+}
+
+#[cfg(test)]
+mod poll {
+    use super::*;
+
+    #[test]
+    fn unwrap_ready_path() {
+        let p = Poll::Ready(42);
+        assert_eq!(Unwrappable::unwrap(p), 42);
+        assert!(Unwrappable::is_success(&p));
+    }
+
+    #[test]
+    #[should_panic(expected = "Poll::Pending")]
+    fn unwrap_pending_path() {
+        let p: Poll<()> = Poll::Pending;
+        Unwrappable::unwrap(p);
+    }
+
+    #[test]
+    #[should_panic(expected = "not ready")]
+    fn expect_pending_path() {
+        let p: Poll<()> = Poll::Pending;
+        Unwrappable::expect(p, "not ready");
+    }
+
+    #[test]
+    fn fallible_combinators() {
+        assert_eq!(
+            Fallible::map_success(Poll::Ready(42), |n| n + 1),
+            Poll::Ready(43)
+        );
+        assert_eq!(
+            Fallible::into_result(Poll::<()>::Pending),
+            Err(PendingError)
+        );
+        assert_eq!(
+            Fallible::or_else(Poll::Pending, |_| Poll::Ready(1)),
+            Poll::Ready(1)
+        );
+    }
+
+    #[test]
+    fn rewrap() {
+        assert_eq!(Unwrappable::rewrap(Poll::Ready(42), 'a'), Poll::Ready('a'));
+        assert_eq!(Unwrappable::rewrap(Poll::<()>::Pending, 'a'), Poll::Pending);
+    }
+}
+
+#[cfg(test)]
+mod poll_result {
+    use super::*;
+
+    use crate::apply::Apply;
+
+    #[test]
+    fn unwrap_ready_ok_path() {
+        let p: Poll<Result<u32, ()>> = Poll::Ready(Ok(42));
+        assert_eq!(Unwrappable::unwrap(p), Ok(42));
+
+        let value: u32 = UnwrapAll::unwrap_all(p);
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "unwrap_all failed at layer 2")]
+    fn unwrap_ready_err_path() {
+        let p: Poll<Result<(), u32>> = Poll::Ready(Err(42));
+        let () = UnwrapAll::unwrap_all(p);
+    }
+
+    #[test]
+    #[should_panic(expected = "unwrap_all failed at layer 1")]
+    fn unwrap_pending_path() {
+        let p: Poll<Result<(), u32>> = Poll::Pending;
+        let () = UnwrapAll::unwrap_all(p);
+    }
+
+    #[test]
+    fn apply_infers_success_type() {
+        let p: Poll<Result<u32, ()>> = Poll::Ready(Ok(42));
+
+        let v = vec![1].apply_unwrap(|_| p);
+        assert_eq!(v, [1]);
+
+        let v = vec![1].apply_try(|_| p);
+        assert_eq!(v, Poll::Ready(vec![1]));
+    }
+}
+
+#[cfg(test)]
+mod control_flow {
+    use super::*;
+
+    #[test]
+    fn unwrap_continue_path() {
+        let c: ControlFlow<(), _> = ControlFlow::Continue(42);
+        assert_eq!(Unwrappable::unwrap(c), 42);
+        assert!(Unwrappable::is_success(&c));
+    }
+
+    #[test]
+    #[should_panic(expected = "ControlFlow::Break")]
+    fn unwrap_break_path() {
+        let c: ControlFlow<_, ()> = ControlFlow::Break(42);
+        Unwrappable::unwrap(c);
+    }
+
+    #[test]
+    #[should_panic(expected = "stopped: 42")]
+    fn expect_break_path() {
+        let c: ControlFlow<_, ()> = ControlFlow::Break(42);
+        Unwrappable::expect(c, "stopped");
+    }
+
+    #[test]
+    fn fallible_combinators() {
+        let c: ControlFlow<&str, u8> = ControlFlow::Continue(42);
+        assert_eq!(
+            Fallible::map_success(c, |n| n + 1),
+            ControlFlow::Continue(43)
+        );
+
+        let c: ControlFlow<&str, u8> = ControlFlow::Break("stop");
+        assert_eq!(Fallible::into_result(c), Err("stop"));
+    }
+
+    #[test]
+    fn rewrap() {
+        let c: ControlFlow<(), _> = ControlFlow::Continue(42);
+        assert_eq!(Unwrappable::rewrap(c, 'a'), ControlFlow::Continue('a'));
+
+        let c: ControlFlow<_, ()> = ControlFlow::Break(42);
+        assert_eq!(Unwrappable::rewrap(c, 'a'), ControlFlow::Break(42));
+    }
+}
+
+#[cfg(test)]
+mod lock_result {
+    use super::*;
+
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    #[test]
+    fn unwrap_lock_path() {
+        let m = Mutex::new(42);
+        assert_eq!(*Unwrappable::unwrap(m.lock()), 42);
+        assert_eq!(*Unwrappable::unwrap(m.try_lock()), 42);
+    }
+
+    #[test]
+    fn try_lock_is_failure_when_locked() {
+        let m = Mutex::new(42);
+        let _guard = m.lock();
+        assert!(!Unwrappable::is_success(&m.try_lock()));
+    }
+
+    #[test]
+    #[should_panic(expected = "PoisonError")]
+    fn unwrap_poisoned_path() {
+        let m = Arc::new(Mutex::new(42));
+        let other = Arc::clone(&m);
+        let _ = thread::spawn(move || {
+            let _guard = other.lock();
+            panic!("poisoning the mutex");
+        })
+        .join();
+
+        let _guard = Unwrappable::unwrap(m.lock());
+    }
+}
+
+#[cfg(test)]
+mod thread_result {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn unwrap_join_path() {
+        let r = thread::spawn(|| 42).join();
+        assert_eq!(Unwrappable::unwrap(r), 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "Any")]
+    fn unwrap_panicked_path() {
+        let r = thread::spawn(|| panic!("thread failed")).join();
+        Unwrappable::unwrap(r);
+    }
 }